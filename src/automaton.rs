@@ -0,0 +1,308 @@
+use crate::cycle::fast_forward_by_fingerprint;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Neighbourhood {
+    Moore(usize),
+    VonNeumann(usize),
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighbourhood {
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        use Neighbourhood::*;
+
+        match self {
+            Moore(radius) | VonNeumann(radius) => {
+                let radius = *radius as isize;
+                let mut offsets = Vec::new();
+
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        if (dx, dy) == (0, 0)
+                            || matches!(self, VonNeumann(_)) && dx.abs() + dy.abs() > radius
+                        {
+                            continue;
+                        }
+
+                        offsets.push((dx, dy));
+                    }
+                }
+
+                offsets
+            }
+            Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Boundary {
+    Bounded,
+    Toroidal,
+    // Cells past an edge mirror the cells inside it, so the first outside cell repeats the edge.
+    Reflecting,
+}
+
+impl Boundary {
+    fn resolve(&self, coordinate: isize, size: usize) -> Option<usize> {
+        use Boundary::*;
+
+        let size = size as isize;
+
+        if (0..size).contains(&coordinate) {
+            return Some(coordinate as usize);
+        }
+
+        match self {
+            Bounded => None,
+            Toroidal => Some(coordinate.rem_euclid(size) as usize),
+            Reflecting => {
+                let folded = coordinate.rem_euclid(2 * size);
+
+                Some(if folded < size {
+                    folded as usize
+                } else {
+                    (2 * size - 1 - folded) as usize
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Grid<C> {
+    width: usize,
+    height: usize,
+    cells: Vec<C>,
+}
+
+impl<C: Copy> Grid<C> {
+    pub fn from_rows(rows: &[Vec<C>]) -> Self {
+        Grid {
+            width: rows.first().map_or(0, |row| row.len()),
+            height: rows.len(),
+            cells: rows.iter().flatten().copied().collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &[C] {
+        &self.cells
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> C {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[C]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn fingerprint(&self) -> u64
+    where
+        C: Hash,
+    {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+pub struct Automaton<R> {
+    offsets: Vec<(isize, isize)>,
+    boundary: Boundary,
+    rule: R,
+}
+
+impl<R> Automaton<R> {
+    pub fn new(neighbourhood: Neighbourhood, boundary: Boundary, rule: R) -> Self {
+        Automaton {
+            offsets: neighbourhood.offsets(),
+            boundary,
+            rule,
+        }
+    }
+
+    pub fn step<C>(&self, grid: &Grid<C>, next_grid: &mut Grid<C>)
+    where
+        C: Copy,
+        R: Fn(C, &[C]) -> C,
+    {
+        let (width, height) = (grid.width, grid.height);
+
+        // Only cells within `radius` of an edge have to consult the boundary.
+        let radius = self
+            .offsets
+            .iter()
+            .map(|(dx, dy)| dx.unsigned_abs().max(dy.unsigned_abs()))
+            .max()
+            .unwrap_or(0);
+        let flat_offsets: Vec<isize> = self
+            .offsets
+            .iter()
+            .map(|(dx, dy)| dy * width as isize + dx)
+            .collect();
+
+        next_grid.width = width;
+        next_grid.height = height;
+        next_grid.cells.clear();
+        next_grid.cells.reserve(grid.cells.len());
+
+        let mut neighbours = Vec::with_capacity(self.offsets.len());
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+
+                neighbours.clear();
+
+                if x >= radius && x + radius < width && y >= radius && y + radius < height {
+                    neighbours.extend(
+                        flat_offsets
+                            .iter()
+                            .map(|offset| grid.cells[index.wrapping_add_signed(*offset)]),
+                    );
+                } else {
+                    for (dx, dy) in self.offsets.iter() {
+                        if let Some(neighbour_x) = self.boundary.resolve(x as isize + dx, width)
+                            && let Some(neighbour_y) =
+                                self.boundary.resolve(y as isize + dy, height)
+                        {
+                            neighbours.push(grid.get(neighbour_x, neighbour_y));
+                        }
+                    }
+                }
+
+                next_grid
+                    .cells
+                    .push((self.rule)(grid.cells[index], &neighbours));
+            }
+        }
+    }
+
+    pub fn advance<C>(&self, grid: &Grid<C>, generations: usize) -> Grid<C>
+    where
+        C: Copy + Eq + Hash,
+        R: Fn(C, &[C]) -> C,
+    {
+        let mut spare_grid = grid.clone();
+
+        fast_forward_by_fingerprint(
+            grid.clone(),
+            generations,
+            |grid| {
+                self.step(grid, &mut spare_grid);
+                std::mem::swap(grid, &mut spare_grid);
+            },
+            Grid::fingerprint,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn life(alive: bool, neighbours: &[bool]) -> bool {
+        let alive_neighbours = neighbours.iter().filter(|neighbour| **neighbour).count();
+
+        alive_neighbours == 3 || alive && alive_neighbours == 2
+    }
+
+    fn parse_grid(input: &str) -> Grid<bool> {
+        let rows: Vec<Vec<bool>> = input
+            .lines()
+            .map(|line| line.chars().map(|char| char == '#').collect())
+            .collect();
+
+        Grid::from_rows(&rows)
+    }
+
+    impl<R: Fn(bool, &[bool]) -> bool> Automaton<R> {
+        fn step_once(&self, grid: &Grid<bool>) -> Grid<bool> {
+            let mut next_grid = grid.clone();
+            self.step(grid, &mut next_grid);
+            next_grid
+        }
+    }
+
+    #[test]
+    fn neighbourhood_sizes() {
+        assert_eq!(Neighbourhood::Moore(1).offsets().len(), 8);
+        assert_eq!(Neighbourhood::Moore(2).offsets().len(), 24);
+        assert_eq!(Neighbourhood::VonNeumann(1).offsets().len(), 4);
+        assert_eq!(Neighbourhood::VonNeumann(2).offsets().len(), 12);
+    }
+
+    #[test]
+    fn toroidal_glider() {
+        let automaton = Automaton::new(Neighbourhood::Moore(1), Boundary::Toroidal, life);
+        let initial_grid = parse_grid(".#...\n..#..\n###..\n.....\n.....");
+
+        // A glider moves one cell diagonally every four generations and wraps around the edges.
+        let mut grid = initial_grid.clone();
+        let mut next_grid = initial_grid.clone();
+
+        for _ in 0..20 {
+            automaton.step(&grid, &mut next_grid);
+            std::mem::swap(&mut grid, &mut next_grid);
+        }
+
+        assert_eq!(grid, initial_grid);
+        assert_eq!(
+            automaton.advance(&initial_grid, 1_000_000_001),
+            automaton.step_once(&initial_grid)
+        );
+    }
+
+    #[test]
+    fn advance_matches_stepping() {
+        let automaton = Automaton::new(Neighbourhood::Moore(1), Boundary::Bounded, life);
+
+        let mut seed: u64 = 42;
+        let rows: Vec<Vec<bool>> = (0..40)
+            .map(|_| {
+                (0..40)
+                    .map(|_| {
+                        seed = seed
+                            .wrapping_mul(6_364_136_223_846_793_005)
+                            .wrapping_add(1_442_695_040_888_963_407);
+                        seed >> 62 == 0
+                    })
+                    .collect()
+            })
+            .collect();
+        let initial_grid = Grid::from_rows(&rows);
+
+        let mut grids = vec![initial_grid.clone()];
+
+        for _ in 0..400 {
+            grids.push(automaton.step_once(grids.last().unwrap()));
+        }
+
+        for generations in (0..=400).step_by(23) {
+            assert_eq!(
+                automaton.advance(&initial_grid, generations),
+                grids[generations]
+            );
+        }
+    }
+
+    #[test]
+    fn boundary_modes() {
+        assert_eq!(Boundary::Bounded.resolve(-1, 5), None);
+        assert_eq!(Boundary::Toroidal.resolve(-1, 5), Some(4));
+        assert_eq!(Boundary::Toroidal.resolve(5, 5), Some(0));
+        assert_eq!(Boundary::Reflecting.resolve(-1, 5), Some(0));
+        assert_eq!(Boundary::Reflecting.resolve(-2, 5), Some(1));
+        assert_eq!(Boundary::Reflecting.resolve(6, 5), Some(3));
+    }
+}
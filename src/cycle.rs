@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    pub fn equivalent_generation(&self, generation: usize) -> usize {
+        if generation < self.start {
+            generation
+        } else {
+            self.start + (generation - self.start) % self.length
+        }
+    }
+}
+
+// Also returns the key that repeated.
+pub fn find_cycle<S, K>(
+    initial: &S,
+    mut step: impl FnMut(&mut S),
+    key: impl Fn(&S) -> K,
+) -> (Cycle, K)
+where
+    S: Clone,
+    K: Eq + Hash,
+{
+    let mut state = initial.clone();
+    let mut generation = 0;
+    let mut seen = HashMap::from([(key(&state), generation)]);
+
+    loop {
+        step(&mut state);
+        generation += 1;
+
+        let state_key = key(&state);
+
+        if let Some(&start) = seen.get(&state_key) {
+            return (
+                Cycle {
+                    start,
+                    length: generation - start,
+                },
+                state_key,
+            );
+        }
+
+        seen.insert(state_key, generation);
+    }
+}
+
+pub fn find_cycle_brent<S, K>(
+    initial: &S,
+    mut step: impl FnMut(&mut S),
+    key: impl Fn(&S) -> K,
+) -> Cycle
+where
+    S: Clone,
+    K: Eq,
+{
+    let mut power = 1;
+    let mut length = 1;
+
+    let mut tortoise_key = key(initial);
+    let mut hare = initial.clone();
+    step(&mut hare);
+
+    loop {
+        let hare_key = key(&hare);
+
+        if hare_key == tortoise_key {
+            break;
+        }
+
+        if power == length {
+            tortoise_key = hare_key;
+            power *= 2;
+            length = 0;
+        }
+
+        step(&mut hare);
+        length += 1;
+    }
+
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+
+    for _ in 0..length {
+        step(&mut hare);
+    }
+
+    let mut start = 0;
+
+    while key(&tortoise) != key(&hare) {
+        step(&mut tortoise);
+        step(&mut hare);
+        start += 1;
+    }
+
+    Cycle { start, length }
+}
+
+pub fn fast_forward<S, K>(
+    initial: S,
+    generations: usize,
+    step: impl FnMut(&mut S),
+    key: impl Fn(&S) -> K,
+) -> S
+where
+    K: Eq + Hash,
+{
+    fast_forward_with_drift(initial, generations, step, key, |_| 0).0
+}
+
+// The second value is the drift that the skipped cycles would have added to the state's own.
+pub fn fast_forward_with_drift<S, K>(
+    initial: S,
+    generations: usize,
+    mut step: impl FnMut(&mut S),
+    key: impl Fn(&S) -> K,
+    drift: impl Fn(&S) -> i64,
+) -> (S, i64)
+where
+    K: Eq + Hash,
+{
+    let mut state = initial;
+    let mut generation = 0;
+    let mut seen = HashMap::from([(key(&state), (generation, drift(&state)))]);
+
+    while generation < generations {
+        step(&mut state);
+        generation += 1;
+
+        let current_drift = drift(&state);
+
+        if let Some((prev_generation, prev_drift)) =
+            seen.insert(key(&state), (generation, current_drift))
+        {
+            let length = generation - prev_generation;
+            let cycles = (generations - generation) / length;
+
+            for _ in 0..(generations - generation) % length {
+                step(&mut state);
+            }
+
+            return (state, cycles as i64 * (current_drift - prev_drift));
+        }
+    }
+
+    (state, 0)
+}
+
+pub fn fast_forward_by_fingerprint<S>(
+    initial: S,
+    generations: usize,
+    mut step: impl FnMut(&mut S),
+    fingerprint: impl Fn(&S) -> u64,
+) -> S
+where
+    S: Clone + Eq,
+{
+    let mut state = initial;
+    let mut generation = 0;
+    let mut fingerprints = HashMap::from([(fingerprint(&state), generation)]);
+
+    while generation < generations {
+        step(&mut state);
+        generation += 1;
+
+        let Some(prev_generation) = fingerprints.insert(fingerprint(&state), generation) else {
+            continue;
+        };
+
+        let length = generation - prev_generation;
+
+        if generation + length > generations {
+            continue;
+        }
+
+        let candidate = state.clone();
+
+        for _ in 0..length {
+            step(&mut state);
+        }
+
+        generation += length;
+
+        if state == candidate {
+            generation += (generations - generation) / length * length;
+        }
+
+        fingerprints.insert(fingerprint(&state), generation);
+    }
+
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_plus_one(value: &mut u32) {
+        *value = (*value * *value + 1) % 255;
+    }
+
+    #[test]
+    fn hash_map_and_brent_agree() {
+        for initial in 0..255 {
+            assert_eq!(
+                find_cycle(&initial, square_plus_one, |value| *value).0,
+                find_cycle_brent(&initial, square_plus_one, |value| *value)
+            );
+        }
+    }
+
+    #[test]
+    fn known_cycle() {
+        // 3 -> 10 -> 101 -> 2 -> 5 -> 26 -> 167 -> 95 -> 101
+        assert_eq!(
+            find_cycle(&3, square_plus_one, |value| *value),
+            (
+                Cycle {
+                    start: 2,
+                    length: 6
+                },
+                101
+            )
+        );
+    }
+
+    #[test]
+    fn fast_forward_matches_stepping() {
+        let mut value = 3;
+
+        for generations in 0..50 {
+            assert_eq!(
+                fast_forward(3, generations, square_plus_one, |value| *value),
+                value
+            );
+            assert_eq!(
+                fast_forward_by_fingerprint(3, generations, square_plus_one, |value| {
+                    *value as u64 % 4
+                }),
+                value
+            );
+
+            square_plus_one(&mut value);
+        }
+    }
+
+    #[test]
+    fn fast_forward_drift() {
+        let step = |(phase, position): &mut (usize, i64)| {
+            *phase = (*phase + 1) % 3;
+
+            if *phase == 0 {
+                *position += 2;
+            }
+        };
+
+        let ((phase, position), offset) = fast_forward_with_drift(
+            (0, 0),
+            1_000_000_000_001,
+            step,
+            |(phase, _)| *phase,
+            |(_, position)| *position,
+        );
+
+        assert_eq!(phase, 1_000_000_000_001 % 3);
+        assert_eq!(position + offset, 1_000_000_000_001 / 3 * 2);
+    }
+}
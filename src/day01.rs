@@ -1,5 +1,5 @@
+use crate::cycle::find_cycle;
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::HashSet;
 
 #[aoc_generator(day1)]
 fn parse_input(input: &str) -> Vec<i32> {
@@ -19,18 +19,16 @@ fn part2(frequency_changes: &[i32]) -> i32 {
     let frequency_change_per_cycle: i32 = frequency_changes.iter().sum();
 
     if frequency_change_per_cycle == 0 {
-        let mut frequency = 0;
-        let mut frequencies = HashSet::from([frequency]);
-
-        for frequency_change in frequency_changes.iter() {
-            frequency += frequency_change;
-
-            if frequencies.contains(&frequency) {
-                return frequency;
-            } else {
-                frequencies.insert(frequency);
-            }
-        }
+        let (_, first_repeated_frequency) = find_cycle(
+            &(0, 0),
+            |(index, frequency): &mut (usize, i32)| {
+                *frequency += frequency_changes[*index];
+                *index = (*index + 1) % frequency_changes.len();
+            },
+            |(_, frequency)| *frequency,
+        );
+
+        return first_repeated_frequency;
     }
 
     let frequencies = frequency_changes
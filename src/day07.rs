@@ -1,5 +1,7 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
 
 #[aoc_generator(day7)]
 fn parse_input(input: &str) -> Vec<(char, char)> {
@@ -8,156 +10,165 @@ fn parse_input(input: &str) -> Vec<(char, char)> {
     parser.parse(input).unwrap()
 }
 
-fn next_and_prev_steps(
-    dependencies: &[(char, char)],
-) -> (
-    BTreeMap<char, BTreeSet<char>>,
-    BTreeMap<char, HashSet<char>>,
-) {
-    dependencies.iter().fold(
-        (BTreeMap::new(), BTreeMap::new()),
-        |(mut next_steps, mut prev_steps), (prev_step, next_step)| {
-            next_steps.entry(*prev_step).or_default().insert(*next_step);
-            prev_steps.entry(*next_step).or_default().insert(*prev_step);
-            (next_steps, prev_steps)
-        },
-    )
+// Ties between equally long tasks go to the task that comes first in order.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Priority {
+    Alphabetical,
+    LongestFirst,
 }
 
-#[aoc(day7, part1)]
-fn part1(dependencies: &[(char, char)]) -> String {
-    let (mut next_steps, mut prev_steps): (
-        BTreeMap<char, BTreeSet<char>>,
-        BTreeMap<char, HashSet<char>>,
-    ) = next_and_prev_steps(dependencies);
-
-    let mut result = String::new();
-
-    while !next_steps.is_empty() {
-        let step = *next_steps
-            .keys()
-            .find(|step| !prev_steps.contains_key(step) || prev_steps.get(step).unwrap().is_empty())
-            .unwrap();
-        let following_steps = next_steps.remove(&step).unwrap();
-
-        for following_step in following_steps.iter() {
-            prev_steps.entry(*following_step).or_default().remove(&step);
-        }
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScheduledTask<T> {
+    pub worker: usize,
+    pub task: T,
+    pub start: u32,
+    pub end: u32,
+}
 
-        prev_steps.remove(&step);
-        result.push(step);
-    }
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Schedule<T> {
+    pub tasks: Vec<ScheduledTask<T>>,
+}
 
-    for step in prev_steps.keys() {
-        result.push(*step);
+impl<T> Schedule<T> {
+    pub fn total_duration(&self) -> u32 {
+        self.tasks.iter().map(|task| task.end).max().unwrap_or(0)
     }
 
-    result
-}
-
-fn step_duration(step: &char, base_step_duration: u32) -> u32 {
-    *step as u32 - 'A' as u32 + 1 + base_step_duration
-}
+    pub fn order(&self) -> impl Iterator<Item = &T> {
+        self.tasks.iter().map(|task| &task.task)
+    }
 
-#[derive(Clone)]
-struct Worker {
-    current_step: Option<char>,
-    remaining_duration: u32,
+    pub fn worker_tasks(&self, worker: usize) -> impl Iterator<Item = &ScheduledTask<T>> {
+        self.tasks.iter().filter(move |task| task.worker == worker)
+    }
 }
 
-fn total_duration(
-    dependencies: &[(char, char)],
+pub fn schedule<T>(
+    dependencies: &[(T, T)],
+    durations: &HashMap<T, u32>,
     num_workers: usize,
-    base_step_duration: u32,
-) -> u32 {
-    let (next_steps, prev_steps): (
-        BTreeMap<char, BTreeSet<char>>,
-        BTreeMap<char, HashSet<char>>,
-    ) = next_and_prev_steps(dependencies);
-
-    let mut in_progress: HashSet<char> = HashSet::new();
-    let mut finished: HashSet<char> = HashSet::new();
-
-    let mut workers = vec![
-        Worker {
-            current_step: None,
-            remaining_duration: 0
-        };
-        num_workers
-    ];
+    priority: Priority,
+) -> Schedule<T>
+where
+    T: Clone + Eq + Hash + Ord,
+{
+    assert!(num_workers > 0, "at least one worker is needed");
+
+    let mut dependents: HashMap<T, Vec<T>> = HashMap::new();
+    let mut prerequisites_left: HashMap<T, usize> =
+        durations.keys().map(|task| (task.clone(), 0)).collect();
+
+    for (before, after) in dependencies {
+        dependents
+            .entry(before.clone())
+            .or_default()
+            .push(after.clone());
+        prerequisites_left.entry(before.clone()).or_insert(0);
+        *prerequisites_left.entry(after.clone()).or_insert(0) += 1;
+    }
 
-    let mut total_duration = 0;
+    let duration = |task: &T| -> u32 {
+        *durations
+            .get(task)
+            .expect("every task must have a duration")
+    };
+    let priority_key = |task: &T| match priority {
+        Priority::Alphabetical => (Reverse(0), task.clone()),
+        Priority::LongestFirst => (Reverse(duration(task)), task.clone()),
+    };
+
+    let mut available: BTreeSet<(Reverse<u32>, T)> = prerequisites_left
+        .iter()
+        .filter(|(_, left)| **left == 0)
+        .map(|(task, _)| priority_key(task))
+        .collect();
+    let mut running: Vec<Option<(T, u32)>> = vec![None; num_workers];
+    let mut tasks = Vec::with_capacity(prerequisites_left.len());
+    let mut time = 0;
 
     loop {
-        for worker in workers.iter_mut() {
-            if worker.current_step.is_none() {
-                if let Some(step) = next_steps
-                    .keys()
-                    .find(|step| {
-                        !in_progress.contains(step)
-                            && !finished.contains(step)
-                            && (!prev_steps.contains_key(step)
-                                || prev_steps
-                                    .get(step)
-                                    .unwrap()
-                                    .iter()
-                                    .all(|prerequisite| finished.contains(prerequisite)))
-                    })
-                    .cloned()
-                {
-                    in_progress.insert(step);
-                    worker.current_step = Some(step);
-                    worker.remaining_duration = step_duration(&step, base_step_duration);
-                } else if let Some(step) = prev_steps
-                    .keys()
-                    .find(|step| {
-                        !in_progress.contains(step)
-                            && !finished.contains(step)
-                            && prev_steps
-                                .get(step)
-                                .unwrap()
-                                .iter()
-                                .all(|prerequisite| finished.contains(prerequisite))
-                    })
-                    .cloned()
-                {
-                    in_progress.insert(step);
-                    worker.current_step = Some(step);
-                    worker.remaining_duration = step_duration(&step, base_step_duration);
-                }
+        for (worker, slot) in running.iter_mut().enumerate() {
+            if slot.is_none()
+                && let Some((_, task)) = available.pop_first()
+            {
+                let end = time + duration(&task);
+
+                tasks.push(ScheduledTask {
+                    worker,
+                    task: task.clone(),
+                    start: time,
+                    end,
+                });
+                *slot = Some((task, end));
             }
         }
 
-        if let Some(min_duration) = workers
-            .iter()
-            .filter_map(|worker| {
-                if worker.current_step.is_some() {
-                    Some(worker.remaining_duration)
-                } else {
-                    None
-                }
-            })
-            .min()
-        {
-            total_duration += min_duration;
-
-            for worker in workers.iter_mut() {
-                if worker.current_step.is_some() {
-                    worker.remaining_duration -= min_duration;
-
-                    if worker.remaining_duration == 0 {
-                        in_progress.remove(&worker.current_step.unwrap());
-                        finished.insert(worker.current_step.unwrap());
-                        worker.current_step = None;
+        let Some(next_time) = running.iter().flatten().map(|(_, end)| *end).min() else {
+            break;
+        };
+
+        time = next_time;
+
+        for slot in running.iter_mut() {
+            if let Some((task, end)) = slot
+                && *end == time
+            {
+                for dependent in dependents.get(task).into_iter().flatten() {
+                    let left = prerequisites_left.get_mut(dependent).unwrap();
+                    *left -= 1;
+
+                    if *left == 0 {
+                        available.insert(priority_key(dependent));
                     }
                 }
+
+                *slot = None;
             }
-        } else {
-            break;
         }
     }
 
-    total_duration
+    assert_eq!(
+        tasks.len(),
+        prerequisites_left.len(),
+        "dependencies must not form a cycle"
+    );
+
+    Schedule { tasks }
+}
+
+fn step_durations(dependencies: &[(char, char)], base_step_duration: u32) -> HashMap<char, u32> {
+    dependencies
+        .iter()
+        .flat_map(|(before, after)| [*before, *after])
+        .map(|step| (step, step as u32 - 'A' as u32 + 1 + base_step_duration))
+        .collect()
+}
+
+#[aoc(day7, part1)]
+fn part1(dependencies: &[(char, char)]) -> String {
+    let durations = step_durations(dependencies, 0)
+        .into_keys()
+        .map(|step| (step, 0))
+        .collect();
+
+    schedule(dependencies, &durations, 1, Priority::Alphabetical)
+        .order()
+        .collect()
+}
+
+fn total_duration(
+    dependencies: &[(char, char)],
+    num_workers: usize,
+    base_step_duration: u32,
+) -> u32 {
+    schedule(
+        dependencies,
+        &step_durations(dependencies, base_step_duration),
+        num_workers,
+        Priority::Alphabetical,
+    )
+    .total_duration()
 }
 
 #[aoc(day7, part2)]
@@ -186,4 +197,52 @@ Step F must be finished before step E can begin.";
     fn part2_example() {
         assert_eq!(total_duration(&parse_input(TEST_INPUT), 2, 0), 15);
     }
+
+    #[test]
+    fn example_schedule() {
+        let dependencies = parse_input(TEST_INPUT);
+        let schedule = schedule(
+            &dependencies,
+            &step_durations(&dependencies, 0),
+            2,
+            Priority::Alphabetical,
+        );
+
+        assert_eq!(
+            schedule
+                .tasks
+                .iter()
+                .map(|task| (task.worker, task.task, task.start, task.end))
+                .collect::<Vec<_>>(),
+            [
+                (0, 'C', 0, 3),
+                (0, 'A', 3, 4),
+                (1, 'F', 3, 9),
+                (0, 'B', 4, 6),
+                (0, 'D', 6, 10),
+                (0, 'E', 10, 15)
+            ]
+        );
+        assert_eq!(schedule.worker_tasks(1).count(), 1);
+    }
+
+    #[test]
+    fn longest_first() {
+        let durations = HashMap::from([(1, 1), (2, 1), (3, 4), (4, 1)]);
+        let dependencies = [(2, 4)];
+
+        let alphabetical = schedule(&dependencies, &durations, 2, Priority::Alphabetical);
+        let longest_first = schedule(&dependencies, &durations, 2, Priority::LongestFirst);
+
+        assert_eq!(
+            alphabetical.order().copied().collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(alphabetical.total_duration(), 5);
+        assert_eq!(
+            longest_first.order().copied().collect::<Vec<_>>(),
+            [3, 1, 2, 4]
+        );
+        assert_eq!(longest_first.total_duration(), 4);
+    }
 }
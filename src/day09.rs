@@ -8,7 +8,212 @@ fn parse_input(input: &str) -> (usize, usize) {
     parser.parse(input).unwrap()
 }
 
-fn winning_score(num_players: usize, last_marble_score: usize) -> usize {
+// A marble's number doubles as its slot in the arena.
+pub struct MarbleCircle {
+    clockwise: Vec<usize>,
+    counter_clockwise: Vec<usize>,
+    current: usize,
+    len: usize,
+}
+
+impl MarbleCircle {
+    pub fn new(capacity: usize) -> Self {
+        let mut clockwise = Vec::with_capacity(capacity);
+        let mut counter_clockwise = Vec::with_capacity(capacity);
+
+        clockwise.push(0);
+        counter_clockwise.push(0);
+
+        MarbleCircle {
+            clockwise,
+            counter_clockwise,
+            current: 0,
+            len: 1,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn rotate_clockwise(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.current = self.clockwise[self.current];
+        }
+    }
+
+    pub fn rotate_counter_clockwise(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.current = self.counter_clockwise[self.current];
+        }
+    }
+
+    pub fn insert(&mut self, marble: usize) {
+        if marble >= self.clockwise.len() {
+            self.clockwise.resize(marble + 1, 0);
+            self.counter_clockwise.resize(marble + 1, 0);
+        }
+
+        let next = self.clockwise[self.current];
+
+        self.clockwise[marble] = next;
+        self.counter_clockwise[marble] = self.current;
+        self.clockwise[self.current] = marble;
+        self.counter_clockwise[next] = marble;
+
+        self.current = marble;
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self) -> Option<usize> {
+        if self.len == 1 {
+            return None;
+        }
+
+        let marble = self.current;
+        let (previous, next) = (self.counter_clockwise[marble], self.clockwise[marble]);
+
+        self.clockwise[previous] = next;
+        self.counter_clockwise[next] = previous;
+
+        self.current = next;
+        self.len -= 1;
+
+        Some(marble)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut marble = self.current;
+
+        (0..self.len).map(move |_| {
+            let current = marble;
+            marble = self.clockwise[marble];
+            current
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GameRules {
+    pub scoring_modulus: usize,
+    pub removal_offset: usize,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            scoring_modulus: 23,
+            removal_offset: 7,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScoringTurn {
+    pub turn: usize,
+    pub player: usize,
+    pub points: usize,
+}
+
+// Players are numbered from zero, so the elf the puzzle calls player 1 is player 0 here.
+pub struct MarbleGame {
+    rules: GameRules,
+    circle: MarbleCircle,
+    scores: Vec<usize>,
+    scoring_turns: Vec<ScoringTurn>,
+    turn: usize,
+}
+
+impl MarbleGame {
+    pub fn new(num_players: usize, last_marble: usize, rules: GameRules) -> Self {
+        // A circle only shrinks on scoring turns, so any modulus above one keeps it non-empty.
+        assert!(
+            rules.scoring_modulus > 1,
+            "scoring modulus must be at least 2"
+        );
+
+        MarbleGame {
+            rules,
+            circle: MarbleCircle::new(last_marble + 1),
+            scores: vec![0; num_players],
+            scoring_turns: Vec::new(),
+            turn: 0,
+        }
+    }
+
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    pub fn circle(&self) -> &MarbleCircle {
+        &self.circle
+    }
+
+    pub fn scores(&self) -> &[usize] {
+        &self.scores
+    }
+
+    pub fn scoring_turns(&self) -> &[ScoringTurn] {
+        &self.scoring_turns
+    }
+
+    pub fn player_scoring_turns(&self, player: usize) -> impl Iterator<Item = usize> + '_ {
+        self.scoring_turns
+            .iter()
+            .filter(move |scoring_turn| scoring_turn.player == player)
+            .map(|scoring_turn| scoring_turn.turn)
+    }
+
+    pub fn winning_score(&self) -> usize {
+        self.scores.iter().copied().max().unwrap_or(0)
+    }
+
+    pub fn play_turn(&mut self) {
+        self.turn += 1;
+
+        let turn = self.turn;
+
+        if !turn.is_multiple_of(self.rules.scoring_modulus) {
+            self.circle.rotate_clockwise(1);
+            self.circle.insert(turn);
+        } else {
+            self.circle
+                .rotate_counter_clockwise(self.rules.removal_offset);
+
+            let player = (turn - 1) % self.scores.len();
+            let points = turn + self.circle.remove().unwrap();
+
+            self.scores[player] += points;
+            self.scoring_turns.push(ScoringTurn {
+                turn,
+                player,
+                points,
+            });
+        }
+    }
+
+    pub fn play_until(&mut self, turn: usize) {
+        while self.turn < turn {
+            self.play_turn();
+        }
+    }
+}
+
+pub fn winning_score(num_players: usize, last_marble_score: usize) -> usize {
+    let mut game = MarbleGame::new(num_players, last_marble_score, GameRules::default());
+    game.play_until(last_marble_score);
+    game.winning_score()
+}
+
+pub fn winning_score_vec_deque(num_players: usize, last_marble_score: usize) -> usize {
     let mut scores = vec![0usize; num_players];
 
     let mut marbles = VecDeque::from([0]);
@@ -38,6 +243,16 @@ fn part2((num_players, last_marble_score): &(usize, usize)) -> usize {
     winning_score(*num_players, *last_marble_score * 100)
 }
 
+#[aoc(day9, part1, VecDeque)]
+fn part1_vec_deque((num_players, last_marble_score): &(usize, usize)) -> usize {
+    winning_score_vec_deque(*num_players, *last_marble_score)
+}
+
+#[aoc(day9, part2, VecDeque)]
+fn part2_vec_deque((num_players, last_marble_score): &(usize, usize)) -> usize {
+    winning_score_vec_deque(*num_players, *last_marble_score * 100)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +273,98 @@ mod tests {
         assert_eq!(part1(&parse_input(TEST_INPUT_4)), 54_718);
         assert_eq!(part1(&parse_input(TEST_INPUT_5)), 37_305);
     }
+
+    #[test]
+    fn linked_list_matches_vec_deque() {
+        for (num_players, last_marble_score) in [(9, 25), (13, 7_999), (30, 58_070)] {
+            assert_eq!(
+                winning_score(num_players, last_marble_score),
+                winning_score_vec_deque(num_players, last_marble_score)
+            );
+        }
+    }
+
+    #[test]
+    fn circle_state() {
+        let mut circle = MarbleCircle::new(26);
+
+        for marble in 1..=22 {
+            circle.rotate_clockwise(1);
+            circle.insert(marble);
+        }
+
+        circle.rotate_counter_clockwise(7);
+
+        assert_eq!(circle.remove(), Some(9));
+        assert_eq!(circle.current(), 19);
+        assert_eq!(circle.len(), 22);
+        assert_eq!(
+            circle.iter().collect::<Vec<_>>(),
+            [
+                19, 2, 20, 10, 21, 5, 22, 11, 1, 12, 6, 13, 3, 14, 7, 15, 0, 16, 8, 17, 4, 18
+            ]
+        );
+    }
+
+    #[test]
+    fn last_marble() {
+        let mut circle = MarbleCircle::new(1);
+
+        assert_eq!(circle.remove(), None);
+        assert_eq!(circle.current(), 0);
+        assert_eq!(circle.len(), 1);
+
+        circle.insert(1);
+
+        assert_eq!(circle.remove(), Some(1));
+        assert_eq!(circle.remove(), None);
+        assert_eq!(circle.iter().collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn score_table() {
+        let mut game = MarbleGame::new(9, 25, GameRules::default());
+        game.play_until(25);
+
+        assert_eq!(game.scores(), [0, 0, 0, 0, 32, 0, 0, 0, 0]);
+        assert_eq!(
+            game.scoring_turns(),
+            [ScoringTurn {
+                turn: 23,
+                player: 4,
+                points: 32
+            }]
+        );
+        assert_eq!(game.player_scoring_turns(4).collect::<Vec<_>>(), [23]);
+        assert_eq!(game.circle().current(), 25);
+        assert_eq!(game.circle().len(), 24);
+    }
+
+    #[test]
+    fn custom_rules() {
+        let rules = GameRules {
+            scoring_modulus: 5,
+            removal_offset: 2,
+        };
+        let mut game = MarbleGame::new(2, 10, rules);
+        game.play_until(10);
+
+        // 0 4 2 1 3 before turn 5, which removes the 3 two steps back from the 4.
+        assert_eq!(
+            game.scoring_turns(),
+            [
+                ScoringTurn {
+                    turn: 5,
+                    player: 0,
+                    points: 8
+                },
+                ScoringTurn {
+                    turn: 10,
+                    player: 1,
+                    points: 18
+                }
+            ]
+        );
+        assert_eq!(game.scores(), [8, 18]);
+    }
 }
@@ -1,12 +1,16 @@
+use crate::ocr::{OcrError, recognize};
 use aoc_runner_derive::{aoc, aoc_generator};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
 
-struct Point {
-    position: (i32, i32),
-    velocity: (i32, i32),
+pub struct Point {
+    pub position: (i32, i32),
+    pub velocity: (i32, i32),
 }
 
 #[aoc_generator(day10)]
-fn parse_input(input: &str) -> Vec<Point> {
+pub fn parse_input(input: &str) -> Vec<Point> {
     use aoc_parse::{parser, prelude::*};
     let parser = parser!(lines(
         "position=<" " "* x:i32 ", " " "* y:i32 "> velocity=<" " "* vx:i32 ", " " "* vy:i32 ">" =>
@@ -15,93 +19,260 @@ fn parse_input(input: &str) -> Vec<Point> {
     parser.parse(input).unwrap()
 }
 
-fn message_and_time(points: &[Point]) -> (String, usize) {
-    let mut positions: Vec<(i32, i32)> = points.iter().map(|point| point.position).collect();
-    let velocities: Vec<(i32, i32)> = points.iter().map(|point| point.velocity).collect();
+pub fn positions_at(points: &[Point], time: i64) -> Vec<(i64, i64)> {
+    points
+        .iter()
+        .map(|point| {
+            (
+                point.position.0 as i64 + point.velocity.0 as i64 * time,
+                point.position.1 as i64 + point.velocity.1 as i64 * time,
+            )
+        })
+        .collect()
+}
 
-    let mut min_x = positions.iter().map(|position| position.0).min().unwrap();
-    let mut max_x = positions.iter().map(|position| position.0).max().unwrap();
-    let mut min_y = positions.iter().map(|position| position.1).min().unwrap();
-    let mut max_y = positions.iter().map(|position| position.1).max().unwrap();
+// Returns the top-left and bottom-right corners, or None when there are no positions.
+fn bounding_box(positions: &[(i64, i64)]) -> Option<((i64, i64), (i64, i64))> {
+    let min_x = positions.iter().map(|position| position.0).min()?;
+    let max_x = positions.iter().map(|position| position.0).max()?;
+    let min_y = positions.iter().map(|position| position.1).min()?;
+    let max_y = positions.iter().map(|position| position.1).max()?;
 
-    let mut width = max_x - min_x + 1;
-    let mut height = max_y - min_y + 1;
+    Some(((min_x, min_y), (max_x, max_y)))
+}
 
-    let mut seconds_count = 0;
+fn bounding_box_size(points: &[Point], time: i64) -> (i64, i64) {
+    bounding_box(&positions_at(points, time)).map_or((0, 0), |((min_x, min_y), (max_x, max_y))| {
+        (max_x - min_x + 1, max_y - min_y + 1)
+    })
+}
 
-    loop {
-        let new_positions: Vec<(i32, i32)> = positions
-            .iter()
-            .zip(velocities.iter())
-            .map(|(position, velocity)| (position.0 + velocity.0, position.1 + velocity.1))
-            .collect();
+fn bounding_box_area(points: &[Point], time: i64) -> i64 {
+    let (width, height) = bounding_box_size(points, time);
+
+    width * height
+}
+
+// The points' spread is quadratic in time, so its minimum has a closed form to start from.
+fn convergence_time(points: &[Point]) -> usize {
+    let count = points.len() as f64;
+    let mean = |value: &dyn Fn(&Point) -> i32| {
+        points.iter().map(|point| value(point) as f64).sum::<f64>() / count
+    };
+
+    let (mean_x, mean_y) = (
+        mean(&|point| point.position.0),
+        mean(&|point| point.position.1),
+    );
+    let (mean_vx, mean_vy) = (
+        mean(&|point| point.velocity.0),
+        mean(&|point| point.velocity.1),
+    );
+
+    let (mut spread_velocity, mut velocity_squared) = (0.0, 0.0);
+
+    for point in points {
+        let (dx, dy) = (
+            point.position.0 as f64 - mean_x,
+            point.position.1 as f64 - mean_y,
+        );
+        let (dvx, dvy) = (
+            point.velocity.0 as f64 - mean_vx,
+            point.velocity.1 as f64 - mean_vy,
+        );
+
+        spread_velocity += dx * dvx + dy * dvy;
+        velocity_squared += dvx * dvx + dvy * dvy;
+    }
+
+    let estimate = if velocity_squared > 0.0 {
+        (-spread_velocity / velocity_squared).round().max(0.0) as i64
+    } else {
+        0
+    };
+
+    let mut time = estimate;
+    let mut area = bounding_box_area(points, time);
+
+    for direction in [-1, 1] {
+        while time + direction >= 0 {
+            let next_area = bounding_box_area(points, time + direction);
+
+            if next_area >= area {
+                break;
+            }
+
+            time += direction;
+            area = next_area;
+        }
+    }
+
+    time as usize
+}
+
+pub const MAX_FRAME_CELLS: i64 = 1 << 24;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FrameError {
+    NoPoints,
+    TooLarge { width: i64, height: i64 },
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use FrameError::*;
+
+        match self {
+            NoPoints => write!(f, "there are no points to draw"),
+            TooLarge { width, height } => write!(
+                f,
+                "a {width}x{height} frame has more than {MAX_FRAME_CELLS} cells"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+pub struct Frame {
+    top_left: (i64, i64),
+    bottom_right: (i64, i64),
+    positions: HashSet<(i64, i64)>,
+}
+
+impl Frame {
+    pub fn at(points: &[Point], time: i64) -> Result<Self, FrameError> {
+        let positions = positions_at(points, time);
+        let (top_left, bottom_right) = bounding_box(&positions).ok_or(FrameError::NoPoints)?;
+        let (width, height) = (
+            bottom_right.0 - top_left.0 + 1,
+            bottom_right.1 - top_left.1 + 1,
+        );
+
+        if width.saturating_mul(height) > MAX_FRAME_CELLS {
+            return Err(FrameError::TooLarge { width, height });
+        }
+
+        Ok(Frame {
+            top_left,
+            bottom_right,
+            positions: positions.into_iter().collect(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        (self.bottom_right.0 - self.top_left.0 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.bottom_right.1 - self.top_left.1 + 1) as usize
+    }
+
+    fn is_lit(&self, column: usize, row: usize) -> bool {
+        self.positions.contains(&(
+            self.top_left.0 + column as i64,
+            self.top_left.1 + row as i64,
+        ))
+    }
+
+    pub fn to_text(&self) -> String {
+        (0..self.height())
+            .map(|row| {
+                (0..self.width())
+                    .map(|column| if self.is_lit(column, row) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Binary PBM, with each row padded to whole bytes.
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut image = format!("P4\n{} {}\n", self.width(), self.height()).into_bytes();
+
+        for row in 0..self.height() {
+            for byte_start in (0..self.width()).step_by(8) {
+                let mut byte = 0u8;
 
-        let new_min_x = new_positions
-            .iter()
-            .map(|position| position.0)
-            .min()
-            .unwrap();
-        let new_max_x = new_positions
-            .iter()
-            .map(|position| position.0)
-            .max()
-            .unwrap();
-        let new_min_y = new_positions
-            .iter()
-            .map(|position| position.1)
-            .min()
-            .unwrap();
-        let new_max_y = new_positions
-            .iter()
-            .map(|position| position.1)
-            .max()
-            .unwrap();
-
-        let new_width = new_max_x - new_min_x + 1;
-        let new_height = new_max_y - new_min_y + 1;
-
-        if new_width >= width && new_height >= height {
-            let mut message = String::new();
-
-            for y in min_y..=max_y {
-                message.push('\n');
-
-                for x in min_x..=max_x {
-                    if positions.contains(&(x, y)) {
-                        message.push('#');
-                    } else {
-                        message.push('.');
+                for bit in 0..8 {
+                    let column = byte_start + bit;
+
+                    if column < self.width() && self.is_lit(column, row) {
+                        byte |= 0x80 >> bit;
                     }
                 }
+
+                image.push(byte);
             }
+        }
 
-            return (message, seconds_count);
-        } else {
-            width = new_width;
-            height = new_height;
-            positions = new_positions;
-            min_x = new_min_x;
-            max_x = new_max_x;
-            min_y = new_min_y;
-            max_y = new_max_y;
-            seconds_count += 1;
+        image
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BoundingBoxSize {
+    pub time: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+pub fn bounding_box_timeline(points: &[Point], times: RangeInclusive<i64>) -> Vec<BoundingBoxSize> {
+    times
+        .map(|time| {
+            let (width, height) = bounding_box_size(points, time);
+
+            BoundingBoxSize {
+                time,
+                width,
+                height,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MessageError {
+    Frame(FrameError),
+    Unreadable(OcrError),
+}
+
+impl Display for MessageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageError::Frame(error) => write!(f, "{error}"),
+            MessageError::Unreadable(error) => write!(f, "{error}"),
         }
     }
 }
 
+impl std::error::Error for MessageError {}
+
+fn message_and_time(points: &[Point]) -> Result<(String, usize), FrameError> {
+    let time = convergence_time(points);
+
+    Ok((
+        format!("\n{}", Frame::at(points, time as i64)?.to_text()),
+        time,
+    ))
+}
+
 #[aoc(day10, part1)]
-fn part1(points: &[Point]) -> String {
-    message_and_time(points).0
+fn part1(points: &[Point]) -> Result<String, MessageError> {
+    let (message, _) = message_and_time(points).map_err(MessageError::Frame)?;
+
+    recognize(&message).map_err(MessageError::Unreadable)
 }
 
 #[aoc(day10, part2)]
 fn part2(points: &[Point]) -> usize {
-    message_and_time(points).1
+    convergence_time(points)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ocr::{EXAMPLE_GLYPHS, recognize_with};
 
     static TEST_INPUT_1: &str = r"position=< 9,  1> velocity=< 0,  2>
 position=< 7,  0> velocity=<-1,  0>
@@ -137,8 +308,20 @@ position=<-3,  6> velocity=< 2, -1>";
 
     #[test]
     fn part1_example() {
+        let points = parse_input(TEST_INPUT_1);
+        let (message, _) = message_and_time(&points).unwrap();
+
+        assert_eq!(
+            recognize_with(&message, EXAMPLE_GLYPHS),
+            Ok(String::from("HI"))
+        );
+        assert!(matches!(part1(&points), Err(MessageError::Unreadable(_))));
+    }
+
+    #[test]
+    fn message_picture() {
         assert_eq!(
-            part1(&parse_input(TEST_INPUT_1)),
+            message_and_time(&parse_input(TEST_INPUT_1)).unwrap().0,
             r"
 #...#..###
 #...#...#.
@@ -150,4 +333,80 @@ position=<-3,  6> velocity=< 2, -1>";
 #...#..###"
         );
     }
+
+    #[test]
+    fn part2_example() {
+        assert_eq!(part2(&parse_input(TEST_INPUT_1)), 3);
+    }
+
+    #[test]
+    fn distant_start() {
+        // The example points rewound by a million seconds.
+        let points: Vec<Point> = parse_input(TEST_INPUT_1)
+            .into_iter()
+            .map(|point| Point {
+                position: (
+                    point.position.0 - point.velocity.0 * 1_000_000,
+                    point.position.1 - point.velocity.1 * 1_000_000,
+                ),
+                velocity: point.velocity,
+            })
+            .collect();
+
+        assert_eq!(
+            message_and_time(&points).unwrap(),
+            (
+                message_and_time(&parse_input(TEST_INPUT_1)).unwrap().0,
+                1_000_003
+            )
+        );
+    }
+
+    #[test]
+    fn frames() {
+        let points = parse_input(TEST_INPUT_1);
+        let frame = Frame::at(&points, 3).unwrap();
+
+        assert_eq!((frame.width(), frame.height()), (10, 8));
+        assert_eq!(frame.to_text().lines().next(), Some("#...#..###"));
+        let pbm = frame.to_pbm();
+
+        // Ten columns take two bytes per row.
+        assert_eq!(pbm.len(), "P4\n10 8\n".len() + 2 * 8);
+        assert_eq!(
+            pbm[pbm.len() - 16..pbm.len() - 14],
+            [0b1000_1001, 0b1100_0000]
+        );
+        assert_eq!(
+            bounding_box_timeline(&points, 2..=4)
+                .iter()
+                .map(|size| (size.time, size.width, size.height))
+                .collect::<Vec<_>>(),
+            [(2, 14, 10), (3, 10, 8), (4, 13, 11)]
+        );
+    }
+
+    #[test]
+    fn frames_out_of_bounds() {
+        let points = vec![
+            Point {
+                position: (-50_000, -50_000),
+                velocity: (0, 0),
+            },
+            Point {
+                position: (50_000, 50_000),
+                velocity: (0, 0),
+            },
+        ];
+
+        assert_eq!(
+            Frame::at(&points, 0).err(),
+            Some(FrameError::TooLarge {
+                width: 100_001,
+                height: 100_001
+            })
+        );
+        assert_eq!(Frame::at(&[], 0).err(), Some(FrameError::NoPoints));
+        assert_eq!(bounding_box_timeline(&[], 0..=0)[0].width, 0);
+    }
 }
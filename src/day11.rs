@@ -10,95 +10,181 @@ fn parse_input(input: &str) -> i32 {
     parser.parse(input).unwrap()
 }
 
-#[aoc(day11, part1)]
-fn part1(grid_serial_number: &i32) -> String {
-    let mut grid = [[0; GRID_SIZE]; GRID_SIZE];
+fn power_level(x: i32, y: i32, grid_serial_number: i32) -> i32 {
+    let rack_id = x + 10;
+
+    (rack_id * y + grid_serial_number) * rack_id / 100 % 10 - 5
+}
+
+// Coordinates are zero-based and point at the top-left cell of the square.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Square {
+    pub x: usize,
+    pub y: usize,
+    pub size: usize,
+    pub total: i32,
+}
 
-    for y in 1..=GRID_SIZE {
-        for x in 1..=GRID_SIZE {
-            grid[y - 1][x - 1] = power_level(x as i32, y as i32, *grid_serial_number);
+// `sums` has a leading row and column of zeros, so edges need no special case.
+pub struct SummedAreaTable {
+    width: usize,
+    height: usize,
+    sums: Vec<i32>,
+}
+
+impl SummedAreaTable {
+    pub fn new(width: usize, height: usize, value: impl Fn(usize, usize) -> i32) -> Self {
+        let mut sums = vec![0; (width + 1) * (height + 1)];
+
+        for y in 1..=height {
+            for x in 1..=width {
+                sums[y * (width + 1) + x] = value(x - 1, y - 1)
+                    + sums[(y - 1) * (width + 1) + x]
+                    + sums[y * (width + 1) + x - 1]
+                    - sums[(y - 1) * (width + 1) + x - 1];
+            }
         }
+
+        SummedAreaTable {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    pub fn from_rows(rows: &[Vec<i32>]) -> Self {
+        SummedAreaTable::new(
+            rows.first().map_or(0, |row| row.len()),
+            rows.len(),
+            |x, y| rows[y][x],
+        )
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn sum_to(&self, x: usize, y: usize) -> i32 {
+        self.sums[y * (self.width + 1) + x]
     }
 
-    let mut max_total_power = grid[0][0]
-        + grid[0][1]
-        + grid[0][2]
-        + grid[1][0]
-        + grid[1][1]
-        + grid[1][2]
-        + grid[2][0]
-        + grid[2][1]
-        + grid[2][2];
-
-    let mut largest_square = (1, 1);
-
-    for y in 1..=GRID_SIZE - SQUARE_SIZE + 1 {
-        for x in 1..=GRID_SIZE - SQUARE_SIZE + 1 {
-            let total_power = grid[y - 1][x - 1]
-                + grid[y - 1][x]
-                + grid[y - 1][x + 1]
-                + grid[y][x - 1]
-                + grid[y][x]
-                + grid[y][x + 1]
-                + grid[y + 1][x - 1]
-                + grid[y + 1][x]
-                + grid[y + 1][x + 1];
-
-            if total_power > max_total_power {
-                max_total_power = total_power;
-                largest_square = (x, y);
+    pub fn rectangle_sum(&self, x: usize, y: usize, width: usize, height: usize) -> i32 {
+        assert!(x + width <= self.width && y + height <= self.height);
+
+        self.sum_to(x + width, y + height) - self.sum_to(x, y + height) - self.sum_to(x + width, y)
+            + self.sum_to(x, y)
+    }
+
+    // Ties go to the topmost, then leftmost square.
+    pub fn best_square(&self, size: usize) -> Option<Square> {
+        let mut best: Option<Square> = None;
+
+        for y in 0..(self.height + 1).saturating_sub(size) {
+            for x in 0..(self.width + 1).saturating_sub(size) {
+                let total = self.rectangle_sum(x, y, size, size);
+
+                if best.is_none_or(|best| total > best.total) {
+                    best = Some(Square { x, y, size, total });
+                }
             }
         }
+
+        best
     }
 
-    format!("{},{}", largest_square.0, largest_square.1)
-}
+    // Ties go to the smallest square. Sizes whose upper bound cannot beat the best are skipped.
+    pub fn best_square_of_any_size(&self, max_cell_value: Option<i32>) -> Option<Square> {
+        let mut best: Option<Square> = None;
+        let mut previous_bound: Option<i32> = None;
 
-fn power_level(x: i32, y: i32, grid_serial_number: i32) -> i32 {
-    let rack_id = x + 10;
+        for size in 1..=self.width.min(self.height) {
+            let bound =
+                max_cell_value
+                    .zip(previous_bound)
+                    .map(|(max_cell_value, previous_bound)| {
+                        previous_bound + (2 * size as i32 - 1) * max_cell_value
+                    });
 
-    (rack_id * y + grid_serial_number) * rack_id / 100 % 10 - 5
-}
+            if let (Some(bound), Some(best)) = (bound, best)
+                && bound <= best.total
+            {
+                previous_bound = Some(bound);
+                continue;
+            }
 
-#[aoc(day11, part2)]
-fn part2(grid_serial_number: &i32) -> String {
-    let mut summed_area = [[0; GRID_SIZE + 1]; GRID_SIZE + 1];
-
-    for y in 1..=GRID_SIZE {
-        for x in 1..=GRID_SIZE {
-            summed_area[y][x] = power_level(x as i32, y as i32, *grid_serial_number)
-                + summed_area[y - 1][x]
-                + summed_area[y][x - 1]
-                - summed_area[y - 1][x - 1];
+            let best_of_size = self.best_square(size).unwrap();
+
+            if best.is_none_or(|best| best_of_size.total > best.total) {
+                best = Some(best_of_size);
+            }
+
+            previous_bound = Some(best_of_size.total);
         }
+
+        best
     }
 
-    let mut max_total_power = summed_area[1][1];
-    let (mut identifier_x, mut identifier_y, mut best_square_size) = (1, 1, 1);
-
-    for square_size in 1..=GRID_SIZE {
-        for y in square_size..=GRID_SIZE {
-            for x in square_size..=GRID_SIZE {
-                let total_power = summed_area[y][x]
-                    - summed_area[y - square_size][x]
-                    - summed_area[y][x - square_size]
-                    + summed_area[y - square_size][x - square_size];
-                if total_power > max_total_power {
-                    max_total_power = total_power;
-                    identifier_x = x;
-                    identifier_y = y;
-                    best_square_size = square_size;
-                }
+    // Sizes are dealt out in turn, so every thread shares the costly small sizes.
+    pub fn best_square_of_any_size_parallel(&self, threads: usize) -> Option<Square> {
+        let threads = threads.max(1);
+        let max_size = self.width.min(self.height);
+
+        let best_squares: Vec<Square> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (1..=threads)
+                .map(|first_size| {
+                    scope.spawn(move || {
+                        (first_size..=max_size)
+                            .step_by(threads)
+                            .filter_map(|size| self.best_square(size))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        best_squares.into_iter().reduce(|best, square| {
+            if square.total > best.total || square.total == best.total && square.size < best.size {
+                square
+            } else {
+                best
             }
-        }
+        })
     }
+}
+
+fn power_grid(grid_serial_number: i32, grid_size: usize) -> SummedAreaTable {
+    SummedAreaTable::new(grid_size, grid_size, |x, y| {
+        power_level(x as i32 + 1, y as i32 + 1, grid_serial_number)
+    })
+}
+
+#[aoc(day11, part1)]
+fn part1(grid_serial_number: &i32) -> String {
+    let square = power_grid(*grid_serial_number, GRID_SIZE)
+        .best_square(SQUARE_SIZE)
+        .unwrap();
+
+    format!("{},{}", square.x + 1, square.y + 1)
+}
 
-    format!(
-        "{},{},{}",
-        identifier_x - best_square_size + 1,
-        identifier_y - best_square_size + 1,
-        best_square_size
-    )
+#[aoc(day11, part2)]
+fn part2(grid_serial_number: &i32) -> String {
+    let square = power_grid(*grid_serial_number, GRID_SIZE)
+        .best_square_of_any_size_parallel(
+            std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        )
+        .unwrap();
+
+    format!("{},{},{}", square.x + 1, square.y + 1, square.size)
 }
 
 #[cfg(test)]
@@ -142,4 +228,48 @@ mod tests {
     fn part2_example2() {
         assert_eq!(part2(&parse_input(TEST_INPUT_2)), "232,251,12");
     }
+
+    #[test]
+    fn rectangle_sums() {
+        let table = SummedAreaTable::from_rows(&[vec![1, 2, 3], vec![4, 5, 6]]);
+
+        assert_eq!(table.rectangle_sum(0, 0, 3, 2), 21);
+        assert_eq!(table.rectangle_sum(1, 0, 2, 2), 16);
+        assert_eq!(table.rectangle_sum(2, 1, 1, 1), 6);
+        assert_eq!(table.rectangle_sum(1, 1, 0, 1), 0);
+        assert_eq!(
+            table.best_square(2),
+            Some(Square {
+                x: 1,
+                y: 0,
+                size: 2,
+                total: 16
+            })
+        );
+        assert_eq!(table.best_square(3), None);
+    }
+
+    #[test]
+    fn pruning_keeps_the_result() {
+        let table = power_grid(18, 60);
+
+        assert_eq!(
+            table.best_square_of_any_size(Some(4)),
+            table.best_square_of_any_size(None)
+        );
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        for grid_serial_number in [18, 42] {
+            let table = power_grid(grid_serial_number, GRID_SIZE);
+
+            for threads in [1, 5] {
+                assert_eq!(
+                    table.best_square_of_any_size_parallel(threads),
+                    table.best_square_of_any_size(None)
+                );
+            }
+        }
+    }
 }
@@ -1,92 +1,369 @@
+use crate::cycle::fast_forward_with_drift;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-enum Pot {
+pub enum Pot {
     Plant,
     Empty,
 }
 
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Unmatched {
+    Empty,
+    Unchanged,
+}
+
+// A `None` in the pattern matches either kind of pot.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Rule {
+    pub pattern: Vec<Option<Pot>>,
+    pub result: Pot,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PatternWidthError {
+    EvenWidth {
+        rule: usize,
+        width: usize,
+    },
+    MixedWidths {
+        rule: usize,
+        width: usize,
+        expected: usize,
+    },
+}
+
+impl Display for PatternWidthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use PatternWidthError::*;
+
+        match self {
+            EvenWidth { rule, width } => {
+                write!(f, "rule {rule} has a pattern of even width {width}")
+            }
+            MixedWidths {
+                rule,
+                width,
+                expected,
+            } => write!(
+                f,
+                "rule {rule} has a pattern of width {width} instead of {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatternWidthError {}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Notes {
+    rules: Vec<Rule>,
+    window_width: usize,
+    pub unmatched: Unmatched,
+}
+
+impl Notes {
+    pub fn new(rules: Vec<Rule>, unmatched: Unmatched) -> Result<Self, PatternWidthError> {
+        let window_width = rules.first().map_or(1, |rule| rule.pattern.len());
+
+        for (rule, Rule { pattern, .. }) in rules.iter().enumerate() {
+            if pattern.len() != window_width {
+                return Err(PatternWidthError::MixedWidths {
+                    rule,
+                    width: pattern.len(),
+                    expected: window_width,
+                });
+            }
+
+            if pattern.len().is_multiple_of(2) {
+                return Err(PatternWidthError::EvenWidth {
+                    rule,
+                    width: pattern.len(),
+                });
+            }
+        }
+
+        Ok(Notes {
+            rules,
+            window_width,
+            unmatched,
+        })
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    fn window_width(&self) -> usize {
+        self.window_width
+    }
+}
+
 #[aoc_generator(day12)]
-fn parse_input(input: &str) -> (Vec<Pot>, HashMap<[Pot; 5], Pot>) {
+pub fn parse_input(input: &str) -> Result<(Vec<Pot>, Notes), PatternWidthError> {
     use Pot::*;
     use aoc_parse::{parser, prelude::*};
     let parser = parser!(
         rule pot: Pot = {'#' => Plant, '.' => Empty};
+        rule pot_pattern: Option<Pot> = {'#' => Some(Plant), '.' => Some(Empty), '?' => None};
 
         section(line("initial state: " pot+))
-        section(hash_map(lines(ll:pot l:pot c:pot r:pot rr:pot " => " n:pot
-            => ([ll, l, c, r, rr], n))))
+        section(lines(pattern:pot_pattern+ " => " result:pot => Rule { pattern, result }))
     );
-    parser.parse(input).unwrap()
+    let (initial_state, rules) = parser.parse(input).unwrap();
+
+    Ok((initial_state, Notes::new(rules, Unmatched::Empty)?))
+}
+
+// An exact pattern takes precedence over any pattern with wildcards.
+struct RuleMatcher<'a> {
+    exact_rules: HashMap<Vec<Pot>, Pot>,
+    wildcard_rules: Vec<&'a Rule>,
+    unmatched: Unmatched,
+}
+
+impl<'a> RuleMatcher<'a> {
+    fn new(notes: &'a Notes) -> Self {
+        RuleMatcher {
+            exact_rules: notes
+                .rules
+                .iter()
+                .filter_map(|rule| {
+                    rule.pattern
+                        .iter()
+                        .copied()
+                        .collect::<Option<Vec<Pot>>>()
+                        .map(|pattern| (pattern, rule.result))
+                })
+                .collect(),
+            wildcard_rules: notes
+                .rules
+                .iter()
+                .filter(|rule| rule.pattern.contains(&None))
+                .collect(),
+            unmatched: notes.unmatched,
+        }
+    }
+
+    fn next_pot(&self, window: &[Pot]) -> Pot {
+        if let Some(result) = self.exact_rules.get(window).or_else(|| {
+            self.wildcard_rules
+                .iter()
+                .find(|rule| {
+                    rule.pattern
+                        .iter()
+                        .zip(window)
+                        .all(|(expected, pot)| expected.is_none_or(|expected| expected == *pot))
+                })
+                .map(|rule| &rule.result)
+        }) {
+            *result
+        } else if self.unmatched == Unmatched::Empty {
+            Pot::Empty
+        } else {
+            window[window.len() / 2]
+        }
+    }
 }
 
-fn sum_of_pot_numbers_after(
+const PACKED_WINDOW_LIMIT: usize = 15;
+
+// Trimmed to start and end with a plant, so equal patterns compare equal wherever they sit.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+struct PackedPots {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PackedPots {
+    fn from_pots(pots: &[Pot]) -> Self {
+        let mut words = vec![0; pots.len().div_ceil(64)];
+
+        for (index, pot) in pots.iter().enumerate() {
+            if *pot == Pot::Plant {
+                words[index / 64] |= 1 << (index % 64);
+            }
+        }
+
+        PackedPots {
+            words,
+            len: pots.len(),
+        }
+    }
+
+    fn bit(&self, index: isize) -> u64 {
+        if index < 0 || index as usize >= self.len {
+            0
+        } else {
+            let index = index as usize;
+            (self.words[index / 64] >> (index % 64)) & 1
+        }
+    }
+
+    fn plant_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|index| self.bit(*index as isize) == 1)
+    }
+
+    fn trim(&mut self) -> usize {
+        let Some(first_word) = self.words.iter().position(|word| *word != 0) else {
+            self.words.clear();
+            self.len = 0;
+            return 0;
+        };
+        let last_word = self.words.iter().rposition(|word| *word != 0).unwrap();
+
+        let first = first_word * 64 + self.words[first_word].trailing_zeros() as usize;
+        let last = last_word * 64 + 63 - self.words[last_word].leading_zeros() as usize;
+        let len = last - first + 1;
+
+        let (word_shift, bit_shift) = (first / 64, first % 64);
+        let mut words = Vec::with_capacity(len.div_ceil(64));
+
+        for index in word_shift..word_shift + len.div_ceil(64) {
+            let low = self.words[index] >> bit_shift;
+            let high = match self.words.get(index + 1) {
+                Some(word) if bit_shift > 0 => word << (64 - bit_shift),
+                _ => 0,
+            };
+
+            words.push(low | high);
+        }
+
+        if !len.is_multiple_of(64) {
+            *words.last_mut().unwrap() &= (1 << (len % 64)) - 1;
+        }
+
+        self.words = words;
+        self.len = len;
+
+        first
+    }
+
+    fn step(&self, table: &[bool], radius: usize, next_pots: &mut PackedPots) {
+        let mask = (1 << (2 * radius + 1)) - 1;
+        let len = self.len + 2 * radius;
+
+        next_pots.words.clear();
+        next_pots.words.resize(len.div_ceil(64), 0);
+        next_pots.len = len;
+
+        let mut window = 0;
+
+        for index in 0..len {
+            window = ((window << 1) | self.bit(index as isize)) & mask;
+
+            if table[window as usize] {
+                next_pots.words[index / 64] |= 1 << (index % 64);
+            }
+        }
+    }
+}
+
+fn compile_rules(notes: &Notes, window_width: usize) -> Vec<bool> {
+    use Pot::*;
+
+    let matcher = RuleMatcher::new(notes);
+    let mut window = vec![Empty; window_width];
+
+    (0..1 << window_width)
+        .map(|combination: usize| {
+            for (offset, pot) in window.iter_mut().enumerate() {
+                *pot = if combination >> (window_width - 1 - offset) & 1 == 1 {
+                    Plant
+                } else {
+                    Empty
+                };
+            }
+
+            matcher.next_pot(&window) == Plant
+        })
+        .collect()
+}
+
+pub fn sum_of_pot_numbers_after(
     initial_state: &[Pot],
-    notes: &HashMap<[Pot; 5], Pot>,
+    notes: &Notes,
     generations_num: usize,
 ) -> i64 {
-    use Pot::*;
+    let window_width = notes.window_width();
+
+    if window_width > PACKED_WINDOW_LIMIT {
+        return sum_of_unpacked_pot_numbers_after(initial_state, notes, generations_num);
+    }
+
+    let radius = window_width / 2;
+    let table = compile_rules(notes, window_width);
+
+    let mut initial_pots = PackedPots::from_pots(initial_state);
+    let initial_shift = -(initial_pots.trim() as i64);
+    let mut spare_pots = initial_pots.clone();
 
-    let mut state = VecDeque::from(initial_state.to_vec());
+    let step = |(pots, shift): &mut (PackedPots, i64)| {
+        pots.step(&table, radius, &mut spare_pots);
+        std::mem::swap(pots, &mut spare_pots);
 
-    let mut shift: i64 = 0;
-    let mut generation = 0;
+        *shift += radius as i64 - pots.trim() as i64;
+    };
 
-    let mut states = HashMap::from([(state.clone(), (shift, generation))]);
+    let ((pots, shift), shift_offset) = fast_forward_with_drift(
+        (initial_pots, initial_shift),
+        generations_num,
+        step,
+        |(pots, _)| pots.clone(),
+        |(_, shift)| *shift,
+    );
+    let shift = shift + shift_offset;
+
+    pots.plant_indices().map(|index| index as i64 - shift).sum()
+}
+
+fn sum_of_unpacked_pot_numbers_after(
+    initial_state: &[Pot],
+    notes: &Notes,
+    generations_num: usize,
+) -> i64 {
+    use Pot::*;
+
+    let radius = notes.window_width() / 2;
+    let matcher = RuleMatcher::new(notes);
 
-    while generation < generations_num {
-        for _ in 0..4 {
+    let step = |(state, shift): &mut (VecDeque<Pot>, i64)| {
+        for _ in 0..2 * radius {
             state.push_front(Empty);
             state.push_back(Empty);
         }
 
-        shift += 4;
+        *shift += 2 * radius as i64;
 
-        let mut new_state = state.clone();
+        let pots = state.make_contiguous();
+        let mut new_state = VecDeque::from(pots.to_vec());
 
-        for index in 2..new_state.len() - 2 {
-            let key: [Pot; 5] = [
-                state[index - 2],
-                state[index - 1],
-                state[index],
-                state[index + 1],
-                state[index + 2],
-            ];
-
-            if notes.contains_key(&key) {
-                new_state[index] = *notes.get(&key).unwrap();
-            } else {
-                new_state[index] = Empty;
-            }
+        for index in radius..new_state.len() - radius {
+            new_state[index] = matcher.next_pot(&pots[index - radius..=index + radius]);
         }
 
         while new_state.front() == Some(&Empty) {
             new_state.pop_front();
-            shift -= 1;
+            *shift -= 1;
         }
 
         while new_state.back() == Some(&Empty) {
             new_state.pop_back();
         }
 
-        generation += 1;
-        state = new_state;
-
-        if states.contains_key(&state) {
-            let (prev_shift, prev_generation) = states.get(&state).unwrap();
-
-            let delta_shift = shift - *prev_shift;
-            let delta_generation = generation - *prev_generation;
+        *state = new_state;
+    };
 
-            let jump = (generations_num - generation) / delta_generation;
-
-            generation += jump * delta_generation;
-            shift += (jump as i64) * delta_shift;
-        } else {
-            states.insert(state.clone(), (shift, generation));
-        }
-    }
+    let ((state, shift), shift_offset) = fast_forward_with_drift(
+        (VecDeque::from(initial_state.to_vec()), 0),
+        generations_num,
+        step,
+        |(state, _)| state.clone(),
+        |(_, shift)| *shift,
+    );
+    let shift = shift + shift_offset;
 
     state
         .iter()
@@ -102,12 +379,12 @@ fn sum_of_pot_numbers_after(
 }
 
 #[aoc(day12, part1)]
-fn part1((initial_state, notes): &(Vec<Pot>, HashMap<[Pot; 5], Pot>)) -> i64 {
+fn part1((initial_state, notes): &(Vec<Pot>, Notes)) -> i64 {
     sum_of_pot_numbers_after(initial_state, notes, 20)
 }
 
 #[aoc(day12, part2)]
-fn part2((initial_state, notes): &(Vec<Pot>, HashMap<[Pot; 5], Pot>)) -> i64 {
+fn part2((initial_state, notes): &(Vec<Pot>, Notes)) -> i64 {
     sum_of_pot_numbers_after(initial_state, notes, 50_000_000_000)
 }
 
@@ -134,6 +411,115 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse_input(TEST_INPUT)), 325);
+        assert_eq!(part1(&parse_input(TEST_INPUT).unwrap()), 325);
+    }
+
+    #[test]
+    fn wildcard_rules() {
+        let (initial_state, notes) = parse_input(
+            r"initial state: #..#.#..##......###...###
+
+?#? => #",
+        )
+        .unwrap();
+
+        assert_eq!(sum_of_pot_numbers_after(&initial_state, &notes, 1_000), 145);
+    }
+
+    #[test]
+    fn unchanged_default() {
+        let (initial_state, mut notes) = parse_input(
+            r"initial state: #
+
+#.. => #",
+        )
+        .unwrap();
+
+        assert_eq!(sum_of_pot_numbers_after(&initial_state, &notes, 10), 10);
+
+        notes.unmatched = Unmatched::Unchanged;
+
+        assert_eq!(sum_of_pot_numbers_after(&initial_state, &notes, 10), 55);
+    }
+
+    #[test]
+    fn wide_window() {
+        let (initial_state, notes) = parse_input(
+            r"initial state: #
+
+??????# => #",
+        )
+        .unwrap();
+
+        assert_eq!(sum_of_pot_numbers_after(&initial_state, &notes, 5), -15);
+    }
+
+    #[test]
+    fn packed_matches_unpacked() {
+        use Pot::*;
+
+        let (initial_state, notes) = parse_input(TEST_INPUT).unwrap();
+
+        for generations in [0, 1, 20, 200] {
+            assert_eq!(
+                sum_of_pot_numbers_after(&initial_state, &notes, generations),
+                sum_of_unpacked_pot_numbers_after(&initial_state, &notes, generations)
+            );
+        }
+
+        // Random rule sets that leave an empty neighbourhood empty, so the pots stay finite.
+        let mut seed: u64 = 7;
+        let mut random_bit = || {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            seed >> 63 == 1
+        };
+
+        for unmatched in [Unmatched::Empty, Unmatched::Unchanged] {
+            for _ in 0..20 {
+                let mut rules = Vec::new();
+
+                for combination in 1..32 {
+                    if random_bit() {
+                        let pattern = (0..5)
+                            .map(|offset| {
+                                Some(if combination >> (4 - offset) & 1 == 1 {
+                                    Plant
+                                } else {
+                                    Empty
+                                })
+                            })
+                            .collect();
+                        let result = if random_bit() { Plant } else { Empty };
+
+                        rules.push(Rule { pattern, result });
+                    }
+                }
+
+                let notes = Notes::new(rules, unmatched).unwrap();
+
+                assert_eq!(
+                    sum_of_pot_numbers_after(&initial_state, &notes, 300),
+                    sum_of_unpacked_pot_numbers_after(&initial_state, &notes, 300)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pattern_widths() {
+        assert_eq!(
+            parse_input("initial state: #\n\n.#.. => #").err(),
+            Some(PatternWidthError::EvenWidth { rule: 0, width: 4 })
+        );
+        assert_eq!(
+            parse_input("initial state: #\n\n.#. => #\n..#.. => #").err(),
+            Some(PatternWidthError::MixedWidths {
+                rule: 1,
+                width: 5,
+                expected: 3
+            })
+        );
     }
 }
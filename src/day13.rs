@@ -1,6 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone)]
@@ -13,7 +13,7 @@ enum Tile {
     Empty,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum Direction {
     Right = 0,
     Down = 1,
@@ -46,14 +46,14 @@ impl Direction {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum Decision {
     TurnLeft = 0,
     GoStraight = 1,
     TurnRight = 2,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 struct Coordinates {
     x: usize,
     y: usize,
@@ -86,7 +86,7 @@ impl From<(usize, usize)> for Coordinates {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 struct Cart {
     direction: Direction,
     next_intersection: Decision,
@@ -101,6 +101,46 @@ impl From<(Direction, Decision)> for Cart {
     }
 }
 
+#[derive(Debug, Eq, PartialEq)]
+enum FirstCrash {
+    At(Coordinates),
+    NoCollision,
+}
+
+impl Display for FirstCrash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FirstCrash::At(coordinates) => write!(f, "{coordinates}"),
+            FirstCrash::NoCollision => write!(f, "no collision"),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum LastCart {
+    At(Coordinates),
+    NoSurvivors,
+    MultipleSurvivors(Vec<Coordinates>),
+}
+
+impl Display for LastCart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LastCart::At(coordinates) => write!(f, "{coordinates}"),
+            LastCart::NoSurvivors => write!(f, "no survivors"),
+            LastCart::MultipleSurvivors(survivors) => {
+                write!(f, "multiple survivors:")?;
+
+                for coordinates in survivors.iter() {
+                    write!(f, " {coordinates}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
 #[aoc_generator(day13)]
 fn parse_input(input: &str) -> (Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>) {
     use Decision::*;
@@ -159,58 +199,70 @@ fn parse_input(input: &str) -> (Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>) {
     (map, carts)
 }
 
-#[aoc(day13, part1)]
-fn part1((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> Coordinates {
+fn move_cart(map: &[Vec<Tile>], coordinates: &Coordinates, cart: &Cart) -> (Coordinates, Cart) {
     use Decision::*;
     use Direction::*;
     use Tile::*;
 
+    let new_coordinates: Coordinates = match cart.direction {
+        Up => (coordinates.x, coordinates.y - 1).into(),
+        Down => (coordinates.x, coordinates.y + 1).into(),
+        Left => (coordinates.x - 1, coordinates.y).into(),
+        Right => (coordinates.x + 1, coordinates.y).into(),
+    };
+
+    let moved_cart = match map[new_coordinates.y][new_coordinates.x] {
+        HorizontalPath | VerticalPath => *cart,
+        PositiveCurve => match cart.direction {
+            Up => (Left, cart.next_intersection).into(),
+            Down => (Right, cart.next_intersection).into(),
+            Left => (Up, cart.next_intersection).into(),
+            Right => (Down, cart.next_intersection).into(),
+        },
+        NegativeCurve => match cart.direction {
+            Up => (Right, cart.next_intersection).into(),
+            Down => (Left, cart.next_intersection).into(),
+            Left => (Down, cart.next_intersection).into(),
+            Right => (Up, cart.next_intersection).into(),
+        },
+        Intersection => (
+            cart.direction.turn(cart.next_intersection),
+            match (cart.next_intersection as usize + 1) % 3 {
+                0 => TurnLeft,
+                1 => GoStraight,
+                2 => TurnRight,
+                _ => unreachable!(),
+            },
+        )
+            .into(),
+        _ => unreachable!(),
+    };
+
+    (new_coordinates, moved_cart)
+}
+
+#[aoc(day13, part1)]
+fn part1((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> FirstCrash {
     let mut carts = carts.clone();
+    let mut seen_states = HashSet::new();
 
     loop {
+        // A repeated state means the carts cycle forever without colliding.
+        if !seen_states.insert(carts.clone()) {
+            return FirstCrash::NoCollision;
+        }
+
         let mut new_carts = carts.clone();
 
         for (coordinates, cart) in carts.iter() {
-            let new_coordinates: Coordinates = match cart.direction {
-                Up => (coordinates.x, coordinates.y - 1).into(),
-                Down => (coordinates.x, coordinates.y + 1).into(),
-                Left => (coordinates.x - 1, coordinates.y).into(),
-                Right => (coordinates.x + 1, coordinates.y).into(),
-            };
+            let (new_coordinates, moved_cart) = move_cart(map, coordinates, cart);
 
             new_carts.remove(coordinates);
 
             if new_carts.contains_key(&new_coordinates) {
-                return new_coordinates;
+                return FirstCrash::At(new_coordinates);
             }
 
-            let moved_cart = match map[new_coordinates.y][new_coordinates.x] {
-                HorizontalPath | VerticalPath => *cart,
-                PositiveCurve => match cart.direction {
-                    Up => (Left, cart.next_intersection).into(),
-                    Down => (Right, cart.next_intersection).into(),
-                    Left => (Up, cart.next_intersection).into(),
-                    Right => (Down, cart.next_intersection).into(),
-                },
-                NegativeCurve => match cart.direction {
-                    Up => (Right, cart.next_intersection).into(),
-                    Down => (Left, cart.next_intersection).into(),
-                    Left => (Down, cart.next_intersection).into(),
-                    Right => (Up, cart.next_intersection).into(),
-                },
-                Intersection => (
-                    cart.direction.turn(cart.next_intersection),
-                    match (cart.next_intersection as usize + 1) % 3 {
-                        0 => TurnLeft,
-                        1 => GoStraight,
-                        2 => TurnRight,
-                        _ => unreachable!(),
-                    },
-                )
-                    .into(),
-                _ => unreachable!(),
-            };
-
             new_carts.insert(new_coordinates, moved_cart);
         }
 
@@ -219,16 +271,19 @@ fn part1((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> Coordi
 }
 
 #[aoc(day13, part2)]
-fn part2((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> Coordinates {
-    use Decision::*;
-    use Direction::*;
-    use Tile::*;
-
+fn part2((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> LastCart {
     let mut carts = carts.clone();
+    let mut seen_states = HashSet::new();
 
     loop {
-        if carts.len() == 1 {
-            return *carts.keys().next().unwrap();
+        match carts.len() {
+            0 => return LastCart::NoSurvivors,
+            1 => return LastCart::At(*carts.keys().next().unwrap()),
+            _ => {}
+        }
+
+        if !seen_states.insert(carts.clone()) {
+            return LastCart::MultipleSurvivors(carts.keys().copied().collect());
         }
 
         let mut new_carts = carts.clone();
@@ -238,50 +293,23 @@ fn part2((map, carts): &(Vec<Vec<Tile>>, BTreeMap<Coordinates, Cart>)) -> Coordi
                 continue;
             }
 
-            let new_coordinates: Coordinates = match cart.direction {
-                Up => (coordinates.x, coordinates.y - 1).into(),
-                Down => (coordinates.x, coordinates.y + 1).into(),
-                Left => (coordinates.x - 1, coordinates.y).into(),
-                Right => (coordinates.x + 1, coordinates.y).into(),
-            };
+            let (new_coordinates, moved_cart) = move_cart(map, coordinates, cart);
 
             new_carts.remove(coordinates);
 
             if let std::collections::btree_map::Entry::Vacant(e) = new_carts.entry(new_coordinates)
             {
-                let moved_cart = match map[new_coordinates.y][new_coordinates.x] {
-                    HorizontalPath | VerticalPath => *cart,
-                    PositiveCurve => match cart.direction {
-                        Up => (Left, cart.next_intersection).into(),
-                        Down => (Right, cart.next_intersection).into(),
-                        Left => (Up, cart.next_intersection).into(),
-                        Right => (Down, cart.next_intersection).into(),
-                    },
-                    NegativeCurve => match cart.direction {
-                        Up => (Right, cart.next_intersection).into(),
-                        Down => (Left, cart.next_intersection).into(),
-                        Left => (Down, cart.next_intersection).into(),
-                        Right => (Up, cart.next_intersection).into(),
-                    },
-                    Intersection => (
-                        cart.direction.turn(cart.next_intersection),
-                        match (cart.next_intersection as usize + 1) % 3 {
-                            0 => TurnLeft,
-                            1 => GoStraight,
-                            2 => TurnRight,
-                            _ => unreachable!(),
-                        },
-                    )
-                        .into(),
-                    _ => unreachable!(),
-                };
-
                 e.insert(moved_cart);
             } else {
                 new_carts.remove(&new_coordinates);
             }
         }
 
+        // States with more carts can never come back once a crash has happened.
+        if new_carts.len() < carts.len() {
+            seen_states.clear();
+        }
+
         carts = new_carts;
     }
 }
@@ -305,11 +333,14 @@ mod tests {
   |   ^
   \<->/";
 
+    static TEST_INPUT_3: &str = r"/>\ /<\
+\-/ \-/";
+
     #[test]
     fn part1_example() {
         assert_eq!(
             part1(&parse_input(TEST_INPUT_1)),
-            Coordinates { x: 7, y: 3 }
+            FirstCrash::At(Coordinates { x: 7, y: 3 })
         );
     }
 
@@ -317,7 +348,23 @@ mod tests {
     fn part2_example() {
         assert_eq!(
             part2(&parse_input(TEST_INPUT_2)),
-            Coordinates { x: 6, y: 4 }
+            LastCart::At(Coordinates { x: 6, y: 4 })
+        );
+    }
+
+    #[test]
+    fn part1_no_collision() {
+        assert_eq!(part1(&parse_input(TEST_INPUT_3)), FirstCrash::NoCollision);
+    }
+
+    #[test]
+    fn part2_multiple_survivors() {
+        assert_eq!(
+            part2(&parse_input(TEST_INPUT_3)),
+            LastCart::MultipleSurvivors(vec![
+                Coordinates { x: 1, y: 0 },
+                Coordinates { x: 5, y: 0 }
+            ])
         );
     }
 }
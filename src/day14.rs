@@ -1,83 +1,254 @@
 use aoc_runner_derive::aoc;
+use std::collections::VecDeque;
 
-#[aoc(day14, part1)]
-fn part1(input: &str) -> String {
-    let num_recipes: usize = input.parse().unwrap();
+pub struct Scoreboard {
+    recipes: Vec<u8>,
+    elves: Vec<usize>,
+    yielded: usize,
+}
 
-    let mut scoreboard = vec![3usize, 7];
-    let mut first_elf_recipe = 0usize;
-    let mut second_elf_recipe = 1usize;
+impl Default for Scoreboard {
+    fn default() -> Self {
+        Scoreboard::new(vec![3, 7], vec![0, 1])
+    }
+}
 
-    let mut ten_recipes = Vec::with_capacity(10);
+impl Scoreboard {
+    pub fn new(recipes: Vec<u8>, elves: Vec<usize>) -> Self {
+        assert!(
+            elves.iter().all(|elf| *elf < recipes.len()),
+            "every elf must start on an existing recipe"
+        );
 
-    while scoreboard.len() <= num_recipes + 10 {
-        let new_recipes = scoreboard[first_elf_recipe] + scoreboard[second_elf_recipe];
+        Scoreboard {
+            recipes,
+            elves,
+            yielded: 0,
+        }
+    }
 
-        if new_recipes / 10 > 0 {
-            if scoreboard.len() >= num_recipes && ten_recipes.len() < 10 {
-                ten_recipes.push(new_recipes / 10);
-            }
+    pub fn recipes(&self) -> &[u8] {
+        &self.recipes
+    }
+
+    fn combine(&mut self) {
+        let sum: usize = self
+            .elves
+            .iter()
+            .map(|elf| self.recipes[*elf] as usize)
+            .sum();
 
-            scoreboard.push(new_recipes / 10);
+        // With more than eleven elves the sum can have more than two digits.
+        let first_new_recipe = self.recipes.len();
+        let mut remaining = sum;
+
+        loop {
+            self.recipes.push((remaining % 10) as u8);
+            remaining /= 10;
+
+            if remaining == 0 {
+                break;
+            }
         }
 
-        if scoreboard.len() >= num_recipes && ten_recipes.len() < 10 {
-            ten_recipes.push(new_recipes % 10);
+        self.recipes[first_new_recipe..].reverse();
+
+        for elf in self.elves.iter_mut() {
+            *elf = (*elf + 1 + self.recipes[*elf] as usize) % self.recipes.len();
         }
+    }
 
-        scoreboard.push(new_recipes % 10);
+    fn extend_to(&mut self, len: usize) {
+        while self.recipes.len() < len {
+            self.combine();
+        }
+    }
 
-        first_elf_recipe = (first_elf_recipe + 1 + scoreboard[first_elf_recipe]) % scoreboard.len();
-        second_elf_recipe =
-            (second_elf_recipe + 1 + scoreboard[second_elf_recipe]) % scoreboard.len();
+    pub fn scores_after(&mut self, num_recipes: usize, count: usize) -> &[u8] {
+        self.extend_to(num_recipes + count);
+        &self.recipes[num_recipes..num_recipes + count]
     }
 
-    let mut result = String::with_capacity(10);
+    pub fn find(&mut self, pattern: &[u8]) -> usize {
+        let search = PatternSearch::new(&[pattern]);
+        let recipes = (0..).map(|index| {
+            self.extend_to(index + 1);
+            self.recipes[index]
+        });
 
-    for recipe in ten_recipes {
-        result.push(char::from_digit(recipe as u32, 10).unwrap());
+        search.matches(recipes).next().unwrap().position
     }
+}
+
+impl Iterator for Scoreboard {
+    type Item = u8;
 
-    result
+    fn next(&mut self) -> Option<u8> {
+        self.extend_to(self.yielded + 1);
+        self.yielded += 1;
+
+        Some(self.recipes[self.yielded - 1])
+    }
 }
 
-#[aoc(day14, part2)]
-fn part2(input: &str) -> usize {
-    let score_sequence: Vec<usize> = input
+fn parse_digits(input: &str) -> Vec<u8> {
+    input
         .chars()
-        .map(|char| char.to_digit(10).unwrap() as usize)
-        .collect();
+        .map(|char| char.to_digit(10).unwrap() as u8)
+        .collect()
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Match {
+    pub pattern: usize,
+    pub position: usize,
+}
+
+const NO_STATE: usize = usize::MAX;
+
+// Aho-Corasick over decimal digits, with failure links folded into the transitions.
+pub struct PatternSearch {
+    transitions: Vec<[usize; 10]>,
+    outputs: Vec<Vec<usize>>,
+    pattern_lens: Vec<usize>,
+}
+
+impl PatternSearch {
+    pub fn new<P: AsRef<[u8]>>(patterns: &[P]) -> Self {
+        let mut transitions = vec![[NO_STATE; 10]];
+        let mut outputs = vec![Vec::new()];
+
+        for (pattern_index, pattern) in patterns.iter().enumerate() {
+            assert!(!pattern.as_ref().is_empty(), "patterns must not be empty");
 
-    let mut scoreboard = vec![3usize, 7];
-    let mut first_elf_recipe = 0usize;
-    let mut second_elf_recipe = 1usize;
+            let mut state = 0;
 
-    loop {
-        let new_recipes = scoreboard[first_elf_recipe] + scoreboard[second_elf_recipe];
+            for digit in pattern.as_ref() {
+                if transitions[state][*digit as usize] == NO_STATE {
+                    transitions[state][*digit as usize] = transitions.len();
+                    transitions.push([NO_STATE; 10]);
+                    outputs.push(Vec::new());
+                }
 
-        if new_recipes / 10 > 0 {
-            scoreboard.push(new_recipes / 10);
+                state = transitions[state][*digit as usize];
+            }
+
+            outputs[state].push(pattern_index);
+        }
+
+        // Breadth-first order completes a failure link's target before the link is followed.
+        let mut failure = vec![0; transitions.len()];
+        let mut queue = VecDeque::new();
+
+        for transition in transitions[0].iter_mut() {
+            match *transition {
+                NO_STATE => *transition = 0,
+                child => queue.push_back(child),
+            }
         }
 
-        scoreboard.push(new_recipes % 10);
+        while let Some(state) = queue.pop_front() {
+            let fallbacks = transitions[failure[state]];
+
+            for (transition, fallback) in transitions[state].iter_mut().zip(fallbacks) {
+                match *transition {
+                    NO_STATE => *transition = fallback,
+                    child => {
+                        failure[child] = fallback;
 
-        first_elf_recipe = (first_elf_recipe + 1 + scoreboard[first_elf_recipe]) % scoreboard.len();
-        second_elf_recipe =
-            (second_elf_recipe + 1 + scoreboard[second_elf_recipe]) % scoreboard.len();
+                        let inherited = outputs[fallback].clone();
+                        outputs[child].extend(inherited);
 
-        if scoreboard.len() >= score_sequence.len()
-            && scoreboard[scoreboard.len() - score_sequence.len()..] == score_sequence
-        {
-            return scoreboard.len() - score_sequence.len();
+                        queue.push_back(child);
+                    }
+                }
+            }
         }
 
-        if scoreboard.len() > score_sequence.len()
-            && scoreboard[scoreboard.len() - score_sequence.len() - 1..scoreboard.len() - 1]
-                == score_sequence
-        {
-            return scoreboard.len() - score_sequence.len() - 1;
+        PatternSearch {
+            transitions,
+            outputs,
+            pattern_lens: patterns
+                .iter()
+                .map(|pattern| pattern.as_ref().len())
+                .collect(),
         }
     }
+
+    pub fn matches<'a>(
+        &'a self,
+        digits: impl IntoIterator<Item = u8> + 'a,
+    ) -> impl Iterator<Item = Match> + 'a {
+        let mut state = 0;
+
+        digits
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(index, digit)| {
+                state = self.transitions[state][digit as usize];
+
+                self.outputs[state].iter().map(move |pattern| Match {
+                    pattern: *pattern,
+                    position: index + 1 - self.pattern_lens[*pattern],
+                })
+            })
+    }
+
+    pub fn all_matches(
+        &self,
+        digits: impl IntoIterator<Item = u8>,
+        limit: usize,
+    ) -> Vec<Vec<usize>> {
+        let mut positions = vec![Vec::new(); self.pattern_lens.len()];
+
+        for found in self.matches(digits.into_iter().take(limit)) {
+            positions[found.pattern].push(found.position);
+        }
+
+        for pattern_positions in positions.iter_mut() {
+            pattern_positions.sort_unstable();
+        }
+
+        positions
+    }
+
+    pub fn first_matches(
+        &self,
+        digits: impl IntoIterator<Item = u8>,
+        limit: usize,
+    ) -> Vec<Option<usize>> {
+        let mut positions = vec![None; self.pattern_lens.len()];
+        let mut remaining = positions.len();
+
+        for found in self.matches(digits.into_iter().take(limit)) {
+            if positions[found.pattern].is_none() {
+                positions[found.pattern] = Some(found.position);
+                remaining -= 1;
+
+                if remaining == 0 {
+                    break;
+                }
+            }
+        }
+
+        positions
+    }
+}
+
+#[aoc(day14, part1)]
+fn part1(input: &str) -> String {
+    let num_recipes: usize = input.parse().unwrap();
+
+    Scoreboard::default()
+        .scores_after(num_recipes, 10)
+        .iter()
+        .map(|score| char::from(b'0' + score))
+        .collect()
+}
+
+#[aoc(day14, part2)]
+fn part2(input: &str) -> usize {
+    Scoreboard::default().find(&parse_digits(input))
 }
 
 #[cfg(test)]
@@ -132,4 +303,51 @@ mod tests {
     fn part2_example4() {
         assert_eq!(part2(TEST_INPUT_8), 2018);
     }
+
+    #[test]
+    fn scoreboard_iterator() {
+        assert_eq!(
+            Scoreboard::default().take(20).collect::<Vec<_>>(),
+            parse_digits("37101012451589167792")
+        );
+    }
+
+    #[test]
+    fn more_elves() {
+        let mut scoreboard = Scoreboard::new(vec![9; 12], (0..12).collect());
+
+        // Twelve nines add up to a three-digit score.
+        assert_eq!(scoreboard.scores_after(12, 3), [1, 0, 8]);
+        assert_eq!(scoreboard.find(&[9, 1, 0, 8]), 11);
+    }
+
+    #[test]
+    fn first_matches_in_one_pass() {
+        let patterns: Vec<Vec<u8>> = [
+            TEST_INPUT_5,
+            TEST_INPUT_6,
+            TEST_INPUT_7,
+            TEST_INPUT_8,
+            "000000",
+        ]
+        .iter()
+        .map(|pattern| parse_digits(pattern))
+        .collect();
+
+        assert_eq!(
+            PatternSearch::new(&patterns).first_matches(Scoreboard::default(), 10_000),
+            [Some(9), Some(5), Some(18), Some(2018), None]
+        );
+    }
+
+    #[test]
+    fn overlapping_matches() {
+        let search = PatternSearch::new(&[[1, 0].as_slice(), &[0, 1], &[1, 0, 1]]);
+
+        // 3 7 1 0 1 0 1 2 4 5 1 5 8 9 1 6 7 7 9 2
+        assert_eq!(
+            search.all_matches(Scoreboard::default(), 20),
+            [vec![2, 4], vec![3, 5], vec![2, 4]]
+        );
+    }
 }
@@ -176,7 +176,7 @@ fn part2((samples, program): &(Vec<Sample>, Program)) -> usize {
             }
         }
 
-        definite_opcodes.extend(extracted.into_iter());
+        definite_opcodes.extend(extracted);
     }
 
     let mut memory_state = [0, 0, 0, 0];
@@ -1,6 +1,7 @@
+use crate::image::ppm;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum VeinComponent {
@@ -9,15 +10,15 @@ enum VeinComponent {
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-struct ClayVein {
+pub struct ClayVein {
     x: VeinComponent,
     y: VeinComponent,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-struct Coordinates {
-    x: i32,
-    y: i32,
+pub struct Coordinates {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl PartialOrd for Coordinates {
@@ -41,10 +42,10 @@ impl From<(i32, i32)> for Coordinates {
     }
 }
 
-const SPRING_COORDINATES: Coordinates = Coordinates { x: 500, y: 0 };
+pub const SPRING_COORDINATES: Coordinates = Coordinates { x: 500, y: 0 };
 
 #[aoc_generator(day17)]
-fn parse_input(input: &str) -> Vec<ClayVein> {
+pub fn parse_input(input: &str) -> Vec<ClayVein> {
     use VeinComponent::*;
     use aoc_parse::{parser, prelude::*};
 
@@ -58,224 +59,659 @@ fn parse_input(input: &str) -> Vec<ClayVein> {
     parser.parse(input).unwrap()
 }
 
-fn is_clay(
-    square: &Coordinates,
-    vertical_veins: &HashMap<i32, Vec<(i32, i32)>>,
-    horizontal_veins: &HashMap<i32, Vec<(i32, i32)>>,
-) -> bool {
-    if let Some(veins) = vertical_veins.get(&square.x) {
-        for range in veins.iter() {
-            if square.y >= range.0 && square.y <= range.1 {
-                return true;
-            }
-        }
-    }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Cell {
+    Sand,
+    Clay,
+    FlowingWater,
+    StillWater,
+}
 
-    if let Some(veins) = horizontal_veins.get(&square.y) {
-        for range in veins.iter() {
-            if square.x >= range.0 && square.x <= range.1 {
-                return true;
-            }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Spring {
+    pub coordinates: Coordinates,
+    pub flow_limit: Option<usize>,
+}
+
+impl From<Coordinates> for Spring {
+    fn from(coordinates: Coordinates) -> Self {
+        Spring {
+            coordinates,
+            flow_limit: None,
         }
     }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct WaterCount {
+    pub wet: usize,
+    pub retained: usize,
+}
 
-    false
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScenarioReport {
+    pub per_spring: Vec<WaterCount>,
+    pub combined: WaterCount,
 }
 
-fn count_water_squares(
-    starting_square: Coordinates,
+#[derive(Clone)]
+pub struct Reservoir {
+    cells: Vec<Cell>,
+    springs: Vec<Coordinates>,
+    owners: Vec<Option<usize>>,
+    min_x: i32,
+    width: usize,
+    top_y: i32,
     min_y: i32,
     max_y: i32,
-    vertical_veins: &HashMap<i32, Vec<(i32, i32)>>,
-    horizontal_veins: &mut HashMap<i32, Vec<(i32, i32)>>,
-    dry: bool,
-) -> usize {
-    let mut starting_points = vec![starting_square];
-    let mut reached = HashSet::new();
-
-    let mut still_water: HashSet<Coordinates> = HashSet::new();
-
-    'main_loop: while let Some(mut current_square) = starting_points.pop() {
-        if current_square.y >= min_y && current_square.y <= max_y {
-            if !reached.contains(&current_square) {
-                reached.insert(current_square);
-            } else {
-                continue;
+}
+
+impl Reservoir {
+    pub fn new(clay_veins: &[ClayVein]) -> Self {
+        use VeinComponent::*;
+
+        let bounds = |component: VeinComponent| match component {
+            Coordinate(value) => (value, value),
+            Range(min, max) => (min, max),
+        };
+
+        let min_x = clay_veins
+            .iter()
+            .map(|vein| bounds(vein.x).0)
+            .min()
+            .unwrap();
+        let max_x = clay_veins
+            .iter()
+            .map(|vein| bounds(vein.x).1)
+            .max()
+            .unwrap();
+        let min_y = clay_veins
+            .iter()
+            .map(|vein| bounds(vein.y).0)
+            .min()
+            .unwrap();
+        let max_y = clay_veins
+            .iter()
+            .map(|vein| bounds(vein.y).1)
+            .max()
+            .unwrap();
+
+        // A one-square margin for overflowing water, plus the rows up to the spring.
+        let top_y = i32::min(min_y - 1, SPRING_COORDINATES.y);
+
+        let mut reservoir = Reservoir {
+            cells: vec![Cell::Sand; ((max_x - min_x + 3) * (max_y - top_y + 1)) as usize],
+            springs: Vec::new(),
+            owners: vec![None; ((max_x - min_x + 3) * (max_y - top_y + 1)) as usize],
+            min_x: min_x - 1,
+            width: (max_x - min_x + 3) as usize,
+            top_y,
+            min_y,
+            max_y,
+        };
+
+        for clay_vein in clay_veins.iter() {
+            let (x_min, x_max) = bounds(clay_vein.x);
+            let (y_min, y_max) = bounds(clay_vein.y);
+
+            for y in y_min..=y_max {
+                for x in x_min..=x_max {
+                    reservoir.set((x, y).into(), Cell::Clay);
+                }
             }
         }
 
-        let mut next_square = (current_square.x, current_square.y + 1).into();
+        reservoir
+    }
 
-        while !is_clay(&next_square, vertical_veins, horizontal_veins) {
-            current_square = next_square;
+    fn index(&self, square: Coordinates) -> Option<usize> {
+        let column = square.x - self.min_x;
+        let row = square.y - self.top_y;
 
-            if current_square.y > max_y {
-                continue 'main_loop;
-            }
+        if column < 0 || column as usize >= self.width || row < 0 || square.y > self.max_y {
+            None
+        } else {
+            Some(row as usize * self.width + column as usize)
+        }
+    }
 
-            if current_square.y >= min_y {
-                if !reached.contains(&current_square) {
-                    reached.insert(current_square);
-                } else {
-                    continue 'main_loop;
+    fn include_column(&mut self, x: i32) {
+        let max_x = self.min_x + self.width as i32 - 1;
+
+        if (self.min_x..=max_x).contains(&x) {
+            return;
+        }
+
+        let min_x = self.min_x.min(x);
+        let width = (max_x.max(x) - min_x + 1) as usize;
+        let offset = (self.min_x - min_x) as usize;
+        let height = self.cells.len() / self.width;
+
+        let mut cells = vec![Cell::Sand; width * height];
+        let mut owners = vec![None; width * height];
+
+        for row in 0..height {
+            let (from, to) = (row * self.width, row * width + offset);
+
+            cells[to..to + self.width].copy_from_slice(&self.cells[from..from + self.width]);
+            owners[to..to + self.width].copy_from_slice(&self.owners[from..from + self.width]);
+        }
+
+        self.cells = cells;
+        self.owners = owners;
+        self.min_x = min_x;
+        self.width = width;
+    }
+
+    pub fn get(&self, square: Coordinates) -> Cell {
+        self.index(square)
+            .map_or(Cell::Sand, |index| self.cells[index])
+    }
+
+    fn set(&mut self, square: Coordinates, cell: Cell) {
+        if let Some(index) = self.index(square) {
+            self.cells[index] = cell;
+        }
+    }
+
+    fn is_solid(&self, square: Coordinates) -> bool {
+        matches!(self.get(square), Cell::Clay | Cell::StillWater)
+    }
+
+    fn wet(
+        &mut self,
+        square: Coordinates,
+        visited: &mut [bool],
+        remaining: &mut Option<usize>,
+    ) -> bool {
+        if let Some(index) = self.index(square) {
+            if self.cells[index] == Cell::Sand {
+                if *remaining == Some(0) {
+                    return false;
+                }
+
+                self.cells[index] = Cell::FlowingWater;
+                self.owners[index] = Some(self.springs.len() - 1);
+
+                if let Some(remaining) = remaining {
+                    *remaining -= 1;
                 }
             }
 
-            next_square = (current_square.x, current_square.y + 1).into();
+            visited[index] = true;
         }
 
-        let mut left_wall = true;
-        let mut right_wall = true;
+        true
+    }
 
-        loop {
-            let mut water_layer_left = current_square.x;
-            let mut water_layer_right = current_square.x;
-            reached.insert(current_square);
+    pub fn fill(&mut self, spring: Coordinates) {
+        self.fill_limited(Spring {
+            coordinates: spring,
+            flow_limit: None,
+        });
+    }
 
-            let mut left_square: Coordinates = (current_square.x - 1, current_square.y).into();
-            let mut below_left_square = (left_square.x, left_square.y + 1).into();
+    pub fn fill_limited(&mut self, spring: Spring) {
+        use Cell::*;
 
-            while !is_clay(&left_square, vertical_veins, horizontal_veins) {
-                if !is_clay(&below_left_square, vertical_veins, horizontal_veins) {
-                    starting_points.push(left_square);
+        let Spring {
+            coordinates: spring,
+            flow_limit: mut remaining,
+        } = spring;
 
-                    left_wall = false;
-                    break;
-                } else {
-                    reached.insert(left_square);
+        self.springs.push(spring);
+        self.include_column(spring.x);
 
-                    water_layer_left = left_square.x;
+        let entry_square = (spring.x, spring.y.max(self.top_y)).into();
 
-                    left_square = (left_square.x - 1, left_square.y).into();
-                    below_left_square = (left_square.x, left_square.y + 1).into();
-                }
+        if self.index(entry_square).is_none() || self.is_solid(entry_square) {
+            return;
+        }
+
+        let mut visited = vec![false; self.cells.len()];
+        let is_visited = |reservoir: &Self, visited: &[bool], square: Coordinates| {
+            reservoir.index(square).is_some_and(|index| visited[index])
+        };
+
+        let mut starting_points = vec![entry_square];
+
+        'main_loop: while let Some(mut current_square) = starting_points.pop() {
+            if self.is_solid(current_square) || is_visited(self, &visited, current_square) {
+                continue;
             }
 
-            let mut right_square: Coordinates = (current_square.x + 1, current_square.y).into();
-            let mut below_right_square = (right_square.x, right_square.y + 1).into();
+            if !self.wet(current_square, &mut visited, &mut remaining) {
+                return;
+            }
 
-            while !is_clay(&right_square, vertical_veins, horizontal_veins) {
-                if !is_clay(&below_right_square, vertical_veins, horizontal_veins) {
-                    starting_points.push(right_square);
+            let mut next_square = (current_square.x, current_square.y + 1).into();
 
-                    right_wall = false;
-                    break;
-                } else {
-                    reached.insert(right_square);
+            while !self.is_solid(next_square) {
+                current_square = next_square;
 
-                    water_layer_right = right_square.x;
+                if current_square.y > self.max_y || is_visited(self, &visited, current_square) {
+                    continue 'main_loop;
+                }
 
-                    right_square = (right_square.x + 1, right_square.y).into();
-                    below_right_square = (right_square.x, right_square.y + 1).into();
+                if !self.wet(current_square, &mut visited, &mut remaining) {
+                    return;
                 }
+
+                next_square = (current_square.x, current_square.y + 1).into();
             }
 
-            if left_wall && right_wall {
-                horizontal_veins
-                    .entry(current_square.y)
-                    .or_default()
-                    .push((water_layer_left, water_layer_right));
+            let mut left_wall = true;
+            let mut right_wall = true;
+
+            loop {
+                let mut water_layer_left = current_square.x;
+                let mut water_layer_right = current_square.x;
+                if !self.wet(current_square, &mut visited, &mut remaining) {
+                    return;
+                }
+
+                let mut left_square: Coordinates = (current_square.x - 1, current_square.y).into();
+                let mut below_left_square = (left_square.x, left_square.y + 1).into();
+
+                while !self.is_solid(left_square) {
+                    if !self.is_solid(below_left_square) {
+                        starting_points.push(left_square);
+
+                        left_wall = false;
+                        break;
+                    } else {
+                        if !self.wet(left_square, &mut visited, &mut remaining) {
+                            return;
+                        }
+
+                        water_layer_left = left_square.x;
+
+                        left_square = (left_square.x - 1, left_square.y).into();
+                        below_left_square = (left_square.x, left_square.y + 1).into();
+                    }
+                }
+
+                let mut right_square: Coordinates = (current_square.x + 1, current_square.y).into();
+                let mut below_right_square = (right_square.x, right_square.y + 1).into();
+
+                while !self.is_solid(right_square) {
+                    if !self.is_solid(below_right_square) {
+                        starting_points.push(right_square);
+
+                        right_wall = false;
+                        break;
+                    } else {
+                        if !self.wet(right_square, &mut visited, &mut remaining) {
+                            return;
+                        }
+
+                        water_layer_right = right_square.x;
+
+                        right_square = (right_square.x + 1, right_square.y).into();
+                        below_right_square = (right_square.x, right_square.y + 1).into();
+                    }
+                }
+
+                if !(left_wall && right_wall) {
+                    break;
+                }
 
                 for x in water_layer_left..=water_layer_right {
-                    still_water.insert((x, current_square.y).into());
+                    self.set((x, current_square.y).into(), StillWater);
                 }
 
                 current_square = (current_square.x, current_square.y - 1).into();
-            } else {
-                break;
+
+                if self.get(current_square) == Clay {
+                    break;
+                }
             }
         }
     }
 
-    if dry {
-        still_water.len()
-    } else {
-        reached.len()
+    pub fn count_water_squares(&self, dry: bool) -> usize {
+        use Cell::*;
+
+        // The rows above the topmost clay lie outside the scan and are never counted.
+        self.cells[(self.min_y - self.top_y) as usize * self.width..]
+            .iter()
+            .filter(|cell| match cell {
+                StillWater => true,
+                FlowingWater => !dry,
+                Sand | Clay => false,
+            })
+            .count()
+    }
+
+    pub fn fill_springs(&mut self, springs: &[Spring]) -> ScenarioReport {
+        use Cell::*;
+
+        let first_spring = self.springs.len();
+
+        for spring in springs.iter() {
+            self.fill_limited(*spring);
+        }
+
+        let mut report = ScenarioReport {
+            per_spring: vec![WaterCount::default(); springs.len()],
+            combined: WaterCount::default(),
+        };
+
+        let scan_start = (self.min_y - self.top_y) as usize * self.width;
+
+        for (cell, owner) in self.cells[scan_start..]
+            .iter()
+            .zip(self.owners[scan_start..].iter())
+        {
+            let Some(owner) = owner.filter(|owner| *owner >= first_spring) else {
+                continue;
+            };
+
+            let counts = &mut report.per_spring[owner - first_spring];
+
+            counts.wet += 1;
+            report.combined.wet += 1;
+
+            if *cell == StillWater {
+                counts.retained += 1;
+                report.combined.retained += 1;
+            }
+        }
+
+        report
+    }
+
+    pub fn to_ppm(&self) -> Vec<u8> {
+        use Cell::*;
+
+        let height = self.cells.len() / self.width;
+
+        ppm(
+            self.width,
+            height,
+            self.cells.iter().enumerate().map(|(index, cell)| {
+                let square: Coordinates = (
+                    self.min_x + (index % self.width) as i32,
+                    self.top_y + (index / self.width) as i32,
+                )
+                    .into();
+
+                if self.springs.contains(&square) {
+                    [255, 0, 0]
+                } else {
+                    match cell {
+                        Sand => [237, 201, 175],
+                        Clay => [120, 72, 40],
+                        FlowingWater => [135, 206, 250],
+                        StillWater => [0, 70, 200],
+                    }
+                }
+            }),
+        )
     }
 }
 
-struct ClayVeinsMap {
-    vertical_veins: HashMap<i32, Vec<(i32, i32)>>,
-    horizontal_veins: HashMap<i32, Vec<(i32, i32)>>,
-    min_y: i32,
-    max_y: i32,
+impl Display for Reservoir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use Cell::*;
+
+        for (row_index, row) in self.cells.chunks(self.width).enumerate() {
+            if row_index > 0 {
+                writeln!(f)?;
+            }
+
+            for (column_index, cell) in row.iter().enumerate() {
+                let square: Coordinates = (
+                    self.min_x + column_index as i32,
+                    self.top_y + row_index as i32,
+                )
+                    .into();
+
+                let symbol = if self.springs.contains(&square) {
+                    '+'
+                } else {
+                    match cell {
+                        Sand => '.',
+                        Clay => '#',
+                        FlowingWater => '|',
+                        StillWater => '~',
+                    }
+                };
+
+                write!(f, "{symbol}")?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
-fn init_map(clay_veins: &[ClayVein]) -> ClayVeinsMap {
-    use VeinComponent::*;
+pub struct WaterFlow {
+    reservoir: Reservoir,
+    frontier: Vec<(Coordinates, usize)>,
+    held_back: Vec<(Coordinates, usize)>,
+    remaining: Vec<Option<usize>>,
+}
 
-    let mut vertical_veins = HashMap::new();
-    let mut horizontal_veins = HashMap::new();
+impl WaterFlow {
+    pub fn new(mut reservoir: Reservoir, springs: &[Spring]) -> Self {
+        let mut frontier = Vec::with_capacity(springs.len());
+        let mut remaining = vec![None; reservoir.springs.len()];
 
-    for clay_vein in clay_veins.iter() {
-        if let Range(x_min, x_max) = clay_vein.x
-            && let Coordinate(y) = clay_vein.y
-        {
-            horizontal_veins
-                .entry(y)
-                .or_insert(Vec::new())
-                .push((x_min, x_max));
-        } else if let Coordinate(x) = clay_vein.x
-            && let Range(y_min, y_max) = clay_vein.y
+        for spring in springs.iter() {
+            let Spring {
+                coordinates: spring,
+                flow_limit,
+            } = *spring;
+            let entry_square = (spring.x, spring.y.max(reservoir.top_y)).into();
+
+            reservoir.springs.push(spring);
+            reservoir.include_column(spring.x);
+            remaining.push(flow_limit);
+
+            if reservoir.index(entry_square).is_some() {
+                frontier.push((entry_square, reservoir.springs.len() - 1));
+            }
+        }
+
+        WaterFlow {
+            reservoir,
+            frontier,
+            held_back: Vec::new(),
+            remaining,
+        }
+    }
+
+    pub fn reservoir(&self) -> &Reservoir {
+        &self.reservoir
+    }
+
+    pub fn into_reservoir(self) -> Reservoir {
+        self.reservoir
+    }
+
+    fn is_exhausted(&self, owner: Option<usize>) -> bool {
+        owner.is_some_and(|owner| self.remaining.get(owner) == Some(&Some(0)))
+    }
+
+    fn can_enter(&self, square: Coordinates, owner: usize) -> bool {
+        use Cell::*;
+
+        let Some(index) = self.reservoir.index(square) else {
+            return false;
+        };
+
+        match self.reservoir.cells[index] {
+            Sand => self.remaining[owner] != Some(0),
+            FlowingWater => {
+                self.is_exhausted(self.reservoir.owners[index]) && !self.is_exhausted(Some(owner))
+            }
+            Clay | StillWater => false,
+        }
+    }
+
+    // Water meeting a live limited spring's water waits for that spring to run dry.
+    fn is_held_back(&self, square: Coordinates, owner: usize) -> bool {
+        let Some(index) = self.reservoir.index(square) else {
+            return false;
+        };
+
+        self.reservoir.cells[index] == Cell::FlowingWater
+            && self.reservoir.owners[index].is_some_and(|other| {
+                other != owner && self.remaining[other].is_some_and(|remaining| remaining > 0)
+            })
+            && !self.is_exhausted(Some(owner))
+    }
+
+    fn spreads_to(&self, square: Coordinates, owner: usize) -> bool {
+        self.can_enter(square, owner) || self.is_held_back(square, owner)
+    }
+
+    fn settled_layer(&self, square: Coordinates) -> Option<(i32, i32)> {
+        use Cell::*;
+
+        let reservoir = &self.reservoir;
+        let rests = |x: i32| {
+            reservoir.get((x, square.y).into()) == FlowingWater
+                && reservoir.is_solid((x, square.y + 1).into())
+        };
+
+        if !rests(square.x) {
+            return None;
+        }
+
+        let mut water_layer_left = square.x;
+
+        while rests(water_layer_left - 1) {
+            water_layer_left -= 1;
+        }
+
+        let mut water_layer_right = square.x;
+
+        while rests(water_layer_right + 1) {
+            water_layer_right += 1;
+        }
+
+        if reservoir.get((water_layer_left - 1, square.y).into()) == Clay
+            && reservoir.get((water_layer_right + 1, square.y).into()) == Clay
         {
-            vertical_veins
-                .entry(x)
-                .or_insert(Vec::new())
-                .push((y_min, y_max));
+            Some((water_layer_left, water_layer_right))
+        } else {
+            None
+        }
+    }
+
+    pub fn step(&mut self) -> Option<&Reservoir> {
+        use Cell::*;
+
+        if self.frontier.is_empty() {
+            return None;
+        }
+
+        let mut active = Vec::with_capacity(self.frontier.len());
+        let mut ran_dry = false;
+
+        for (square, owner) in std::mem::take(&mut self.frontier) {
+            if !self.can_enter(square, owner) {
+                if self.is_held_back(square, owner) {
+                    self.held_back.push((square, owner));
+                }
+
+                continue;
+            }
+
+            let index = self.reservoir.index(square).unwrap();
+
+            if self.reservoir.cells[index] == Sand
+                && let Some(remaining) = &mut self.remaining[owner]
+            {
+                *remaining -= 1;
+                ran_dry |= *remaining == 0;
+            }
+
+            self.reservoir.cells[index] = FlowingWater;
+            self.reservoir.owners[index] = Some(owner);
+            active.push(square);
+        }
+
+        let mut unsettled = active.clone();
+
+        while let Some(square) = unsettled.pop() {
+            if let Some((water_layer_left, water_layer_right)) = self.settled_layer(square) {
+                for x in water_layer_left..=water_layer_right {
+                    self.reservoir.set((x, square.y).into(), StillWater);
+
+                    let above_square = (x, square.y - 1).into();
+
+                    if self.reservoir.get(above_square) == FlowingWater {
+                        unsettled.push(above_square);
+                        active.push(above_square);
+                    }
+                }
+            }
+        }
+
+        for square in active {
+            let Some(index) = self.reservoir.index(square) else {
+                continue;
+            };
+
+            if self.reservoir.cells[index] != FlowingWater {
+                continue;
+            }
+
+            let owner = self.reservoir.owners[index].unwrap();
+            let below_square: Coordinates = (square.x, square.y + 1).into();
+
+            if below_square.y > self.reservoir.max_y {
+                continue;
+            }
+
+            if !self.reservoir.is_solid(below_square) {
+                if self.spreads_to(below_square, owner) {
+                    self.frontier.push((below_square, owner));
+                }
+            } else {
+                for side_square in [(square.x - 1, square.y), (square.x + 1, square.y)] {
+                    let side_square = side_square.into();
+
+                    if self.spreads_to(side_square, owner) {
+                        self.frontier.push((side_square, owner));
+                    }
+                }
+            }
+        }
+
+        if ran_dry {
+            self.frontier.append(&mut self.held_back);
         }
+
+        Some(&self.reservoir)
     }
+}
 
-    let min_y = clay_veins
-        .iter()
-        .map(|vein| match vein.y {
-            Coordinate(y) => y,
-            Range(y_min, _) => y_min,
-        })
-        .min()
-        .unwrap();
-    let max_y = clay_veins
-        .iter()
-        .map(|vein| match vein.y {
-            Coordinate(y) => y,
-            Range(_, y_max) => y_max,
-        })
-        .max()
-        .unwrap();
-
-    ClayVeinsMap {
-        horizontal_veins,
-        vertical_veins,
-        min_y,
-        max_y,
+impl Iterator for WaterFlow {
+    type Item = Reservoir;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().cloned()
     }
 }
 
+pub fn fill_reservoir(clay_veins: &[ClayVein]) -> Reservoir {
+    let mut reservoir = Reservoir::new(clay_veins);
+    reservoir.fill(SPRING_COORDINATES);
+    reservoir
+}
+
 #[aoc(day17, part1)]
 fn part1(clay_veins: &[ClayVein]) -> usize {
-    let mut clay_veins_map = init_map(clay_veins);
-
-    count_water_squares(
-        SPRING_COORDINATES,
-        clay_veins_map.min_y,
-        clay_veins_map.max_y,
-        &clay_veins_map.vertical_veins,
-        &mut clay_veins_map.horizontal_veins,
-        false,
-    )
+    fill_reservoir(clay_veins).count_water_squares(false)
 }
 
 #[aoc(day17, part2)]
 fn part2(clay_veins: &[ClayVein]) -> usize {
-    let mut clay_veins_map = init_map(clay_veins);
-
-    count_water_squares(
-        SPRING_COORDINATES,
-        clay_veins_map.min_y,
-        clay_veins_map.max_y,
-        &clay_veins_map.vertical_veins,
-        &mut clay_veins_map.horizontal_veins,
-        true,
-    )
+    fill_reservoir(clay_veins).count_water_squares(true)
 }
 
 #[cfg(test)]
@@ -300,4 +736,195 @@ y=13, x=498..504";
     fn part2_example() {
         assert_eq!(part2(&parse_input(TEST_INPUT)), 29);
     }
+
+    #[test]
+    fn render_example() {
+        assert_eq!(
+            fill_reservoir(&parse_input(TEST_INPUT)).to_string(),
+            r"......+.......
+......|.....#.
+.#..#||||...#.
+.#..#~~#|.....
+.#..#~~#|.....
+.#~~~~~#|.....
+.#~~~~~#|.....
+.#######|.....
+........|.....
+...|||||||||..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#######|.."
+        );
+    }
+
+    #[test]
+    fn ppm_example() {
+        let image = fill_reservoir(&parse_input(TEST_INPUT)).to_ppm();
+        let header = b"P6\n14 14\n255\n";
+
+        assert!(image.starts_with(header));
+        assert_eq!(image.len(), header.len() + 14 * 14 * 3);
+        assert_eq!(
+            image[header.len() + 6 * 3..header.len() + 7 * 3],
+            [255, 0, 0]
+        );
+    }
+
+    #[test]
+    fn two_springs() {
+        let mut reservoir = Reservoir::new(&parse_input(TEST_INPUT));
+
+        let report = reservoir.fill_springs(&[
+            SPRING_COORDINATES.into(),
+            Coordinates { x: 496, y: 0 }.into(),
+            SPRING_COORDINATES.into(),
+        ]);
+
+        assert_eq!(
+            report,
+            ScenarioReport {
+                per_spring: vec![
+                    WaterCount {
+                        wet: 57,
+                        retained: 29
+                    },
+                    WaterCount {
+                        wet: 24,
+                        retained: 6
+                    },
+                    WaterCount::default(),
+                ],
+                combined: WaterCount {
+                    wet: 81,
+                    retained: 35
+                },
+            }
+        );
+        assert_eq!(reservoir.count_water_squares(false), 81);
+    }
+
+    #[test]
+    fn limited_spring() {
+        let mut reservoir = Reservoir::new(&parse_input(TEST_INPUT));
+
+        let report = reservoir.fill_springs(&[Spring {
+            coordinates: SPRING_COORDINATES,
+            flow_limit: Some(5),
+        }]);
+
+        assert_eq!(
+            report.combined,
+            WaterCount {
+                wet: 4,
+                retained: 0
+            }
+        );
+    }
+
+    #[test]
+    fn limited_then_unlimited_spring() {
+        let mut reservoir = Reservoir::new(&parse_input(TEST_INPUT));
+
+        let report = reservoir.fill_springs(&[
+            Spring {
+                coordinates: SPRING_COORDINATES,
+                flow_limit: Some(5),
+            },
+            SPRING_COORDINATES.into(),
+        ]);
+
+        assert_eq!(report.per_spring[0].wet, 4);
+        assert_eq!(report.per_spring[1].wet, 53);
+        assert_eq!(
+            report.combined,
+            WaterCount {
+                wet: 57,
+                retained: 29
+            }
+        );
+        assert_eq!(
+            reservoir.to_string(),
+            fill_reservoir(&parse_input(TEST_INPUT)).to_string()
+        );
+    }
+
+    #[test]
+    fn water_flow_example() {
+        let clay_veins = parse_input(TEST_INPUT);
+        let mut flow = WaterFlow::new(Reservoir::new(&clay_veins), &[SPRING_COORDINATES.into()]);
+
+        assert_eq!(flow.step().unwrap().count_water_squares(false), 0);
+        assert_eq!(flow.step().unwrap().count_water_squares(false), 1);
+
+        while flow.step().is_some() {}
+
+        let final_state = flow.into_reservoir();
+
+        assert_eq!(
+            final_state.to_string(),
+            fill_reservoir(&clay_veins).to_string()
+        );
+        assert_eq!(final_state.count_water_squares(false), 57);
+        assert_eq!(final_state.count_water_squares(true), 29);
+    }
+
+    #[test]
+    fn water_flow_states() {
+        let clay_veins = parse_input(TEST_INPUT);
+        let flow = WaterFlow::new(Reservoir::new(&clay_veins), &[SPRING_COORDINATES.into()]);
+        let states: Vec<Reservoir> = flow.collect();
+
+        assert_eq!(
+            states
+                .iter()
+                .take(2)
+                .map(|state| state.count_water_squares(false))
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(
+            states.last().unwrap().to_string(),
+            fill_reservoir(&clay_veins).to_string()
+        );
+    }
+
+    #[test]
+    fn water_flow_limited_spring() {
+        let clay_veins = parse_input(TEST_INPUT);
+        let mut flow = WaterFlow::new(
+            Reservoir::new(&clay_veins),
+            &[
+                Spring {
+                    coordinates: SPRING_COORDINATES,
+                    flow_limit: Some(5),
+                },
+                Spring {
+                    coordinates: Coordinates { x: 500, y: -10 },
+                    flow_limit: None,
+                },
+            ],
+        );
+
+        while flow.step().is_some() {}
+
+        assert_eq!(flow.reservoir().count_water_squares(false), 57);
+        assert_eq!(flow.reservoir().count_water_squares(true), 29);
+    }
+
+    #[test]
+    fn spring_beside_the_clay() {
+        let clay_veins = parse_input("x=490, y=12..15\ny=2, x=492..498\nx=494, y=6..10");
+
+        assert_eq!(part1(&clay_veins), 14);
+        assert_eq!(part2(&clay_veins), 0);
+    }
+
+    #[test]
+    fn large_basin() {
+        let clay_veins = parse_input("x=0, y=1..1000\nx=1000, y=1..1000\ny=1000, x=0..1000");
+
+        assert_eq!(part1(&clay_veins), 999 * 999 + 2 * 1_000);
+        assert_eq!(part2(&clay_veins), 999 * 999);
+    }
 }
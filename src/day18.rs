@@ -1,15 +1,19 @@
+use crate::automaton::{Automaton, Boundary, Grid, Neighbourhood};
+use crate::cycle::Cycle;
+use crate::image::ppm;
 use aoc_runner_derive::{aoc, aoc_generator};
 use std::collections::HashMap;
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
-enum Acre {
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[repr(u8)]
+pub enum Acre {
     OpenGround,
     Trees,
     Lumberyard,
 }
 
 #[aoc_generator(day18)]
-fn parse_input(input: &str) -> Vec<Vec<Acre>> {
+pub fn parse_input(input: &str) -> Vec<Vec<Acre>> {
     use Acre::*;
 
     let mut scan = Vec::with_capacity(input.lines().count());
@@ -32,98 +36,216 @@ fn parse_input(input: &str) -> Vec<Vec<Acre>> {
     scan
 }
 
-fn adjacent_acres(scan: &[Vec<Acre>], x: usize, y: usize) -> (usize, usize, usize) {
+fn lumber_collection(acre: Acre, adjacent_acres: &[Acre]) -> Acre {
     use Acre::*;
 
-    let (mut open_ground_count, mut trees_count, mut lumberyard_count) = (0, 0, 0);
-
-    for adj_y in i32::max(y as i32 - 1, 0)..=i32::min(y as i32 + 1, scan.len() as i32 - 1) {
-        for adj_x in i32::max(x as i32 - 1, 0)..=i32::min(x as i32 + 1, scan[0].len() as i32 - 1) {
-            if adj_x as usize == x && adj_y as usize == y {
-                continue;
+    let trees_count = adjacent_acres.iter().filter(|acre| **acre == Trees).count();
+    let lumberyard_count = adjacent_acres
+        .iter()
+        .filter(|acre| **acre == Lumberyard)
+        .count();
+
+    match acre {
+        OpenGround => {
+            if trees_count >= 3 {
+                Trees
+            } else {
+                OpenGround
             }
-
-            match scan[adj_y as usize][adj_x as usize] {
-                OpenGround => open_ground_count += 1,
-                Trees => trees_count += 1,
-                Lumberyard => lumberyard_count += 1,
+        }
+        Trees => {
+            if lumberyard_count >= 3 {
+                Lumberyard
+            } else {
+                Trees
+            }
+        }
+        Lumberyard => {
+            if lumberyard_count >= 1 && trees_count >= 1 {
+                Lumberyard
+            } else {
+                OpenGround
             }
         }
     }
+}
+
+type LumberAutomaton = Automaton<fn(Acre, &[Acre]) -> Acre>;
 
-    (open_ground_count, trees_count, lumberyard_count)
+fn lumber_automaton() -> LumberAutomaton {
+    Automaton::new(
+        Neighbourhood::Moore(1),
+        Boundary::Bounded,
+        lumber_collection,
+    )
 }
 
-fn resource_value_after(scan: &[Vec<Acre>], minutes: usize) -> usize {
-    use Acre::*;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ResourceCounts {
+    pub trees: usize,
+    pub lumberyards: usize,
+}
+
+impl ResourceCounts {
+    fn of(scan: &Grid<Acre>) -> Self {
+        use Acre::*;
+
+        ResourceCounts {
+            trees: scan.cells().iter().filter(|acre| **acre == Trees).count(),
+            lumberyards: scan
+                .cells()
+                .iter()
+                .filter(|acre| **acre == Lumberyard)
+                .count(),
+        }
+    }
 
-    let mut scan = scan.to_owned();
-    let mut minute: usize = 0;
-
-    let mut scans_cache = HashMap::from([(scan.clone(), minute)]);
-
-    loop {
-        minute += 1;
-
-        let mut new_scan = Vec::with_capacity(scan.len());
-
-        for y in 0..scan.len() {
-            let mut new_scan_row = Vec::with_capacity(scan[0].len());
-
-            for x in 0..scan[0].len() {
-                let (_open_ground_count, trees_count, lumberyard_count) =
-                    adjacent_acres(&scan, x, y);
-
-                new_scan_row.push(match scan[y][x] {
-                    OpenGround => {
-                        if trees_count >= 3 {
-                            Trees
-                        } else {
-                            OpenGround
-                        }
-                    }
-                    Trees => {
-                        if lumberyard_count >= 3 {
-                            Lumberyard
-                        } else {
-                            Trees
-                        }
-                    }
-                    Lumberyard => {
-                        if lumberyard_count >= 1 && trees_count >= 1 {
-                            Lumberyard
-                        } else {
-                            OpenGround
-                        }
-                    }
-                });
+    pub fn resource_value(&self) -> usize {
+        self.trees * self.lumberyards
+    }
+}
+
+pub struct LumberHistory {
+    automaton: LumberAutomaton,
+    initial_scan: Grid<Acre>,
+    counts: Vec<ResourceCounts>,
+    cycle: Cycle,
+    cycle_scans: Vec<Grid<Acre>>,
+}
+
+impl LumberHistory {
+    pub fn record(scan: &[Vec<Acre>]) -> Self {
+        Self::record_with(scan, Grid::fingerprint)
+    }
+
+    fn record_with(scan: &[Vec<Acre>], fingerprint: impl Fn(&Grid<Acre>) -> u64) -> Self {
+        let automaton = lumber_automaton();
+        let initial_scan = Grid::from_rows(scan);
+
+        let mut scan = initial_scan.clone();
+        let mut spare_scan = initial_scan.clone();
+        let mut step = |scan: &mut Grid<Acre>| {
+            automaton.step(scan, &mut spare_scan);
+            std::mem::swap(scan, &mut spare_scan);
+        };
+
+        let mut counts = vec![ResourceCounts::of(&scan)];
+        let mut fingerprints: HashMap<u64, Vec<usize>> =
+            HashMap::from([(fingerprint(&scan), vec![0])]);
+
+        let cycle = loop {
+            step(&mut scan);
+            counts.push(ResourceCounts::of(&scan));
+
+            let minute = counts.len() - 1;
+            let earlier_minutes = fingerprints.entry(fingerprint(&scan)).or_default();
+            let start = earlier_minutes
+                .iter()
+                .copied()
+                .find(|start| automaton.advance(&initial_scan, *start) == scan);
+
+            match start {
+                Some(start) => {
+                    break Cycle {
+                        start,
+                        length: minute - start,
+                    };
+                }
+                None => earlier_minutes.push(minute),
             }
+        };
+
+        let mut cycle_scans = Vec::with_capacity(cycle.length);
 
-            new_scan.push(new_scan_row);
+        for _ in 0..cycle.length {
+            cycle_scans.push(scan.clone());
+            step(&mut scan);
         }
 
-        scan = new_scan;
+        counts.truncate(cycle.start + cycle.length);
 
-        if minute == minutes {
-            break;
+        LumberHistory {
+            automaton,
+            initial_scan,
+            counts,
+            cycle,
+            cycle_scans,
         }
+    }
+
+    pub fn cycle(&self) -> Cycle {
+        self.cycle
+    }
+
+    pub fn counts_at(&self, minute: usize) -> ResourceCounts {
+        self.counts[self.cycle.equivalent_generation(minute)]
+    }
 
-        if let Some(prev_minute) = scans_cache.get(&scan) {
-            let delta_minutes = minute - *prev_minute;
-            let jump = (minutes - minute) / delta_minutes;
+    // Minutes before the cycle are simulated again from the initial scan.
+    pub fn scan_at(&self, minute: usize) -> Grid<Acre> {
+        if minute < self.cycle.start {
+            self.automaton.advance(&self.initial_scan, minute)
+        } else {
+            self.cycle_scans[(minute - self.cycle.start) % self.cycle.length].clone()
+        }
+    }
 
-            minute += jump * delta_minutes;
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("minute,trees,lumberyards,resource_value,in_cycle\n");
+
+        for (minute, counts) in self.counts.iter().enumerate() {
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                minute,
+                counts.trees,
+                counts.lumberyards,
+                counts.resource_value(),
+                minute >= self.cycle.start
+            ));
         }
 
-        scans_cache.insert(scan.clone(), minute);
+        csv
     }
 
-    scan.iter().flatten().filter(|acre| **acre == Trees).count()
-        * scan
-            .iter()
-            .flatten()
-            .filter(|acre| **acre == Lumberyard)
-            .count()
+    pub fn render(&self, minute: usize) -> String {
+        use Acre::*;
+
+        self.scan_at(minute)
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|acre| match acre {
+                        OpenGround => '.',
+                        Trees => '|',
+                        Lumberyard => '#',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_ppm(&self, minute: usize) -> Vec<u8> {
+        use Acre::*;
+
+        let scan = self.scan_at(minute);
+
+        ppm(
+            scan.width(),
+            scan.height(),
+            scan.cells().iter().map(|acre| match acre {
+                OpenGround => [222, 205, 160],
+                Trees => [34, 139, 34],
+                Lumberyard => [110, 70, 30],
+            }),
+        )
+    }
+}
+
+fn resource_value_after(scan: &[Vec<Acre>], minutes: usize) -> usize {
+    let scan = lumber_automaton().advance(&Grid::from_rows(scan), minutes);
+
+    ResourceCounts::of(&scan).resource_value()
 }
 
 #[aoc(day18, part1)]
@@ -155,4 +277,77 @@ mod tests {
     fn part1_example() {
         assert_eq!(part1(&parse_input(TEST_INPUT)), 1_147);
     }
+
+    #[test]
+    fn history_example() {
+        let scan = parse_input(TEST_INPUT);
+        let history = LumberHistory::record(&scan);
+
+        assert_eq!(
+            history.counts_at(10),
+            ResourceCounts {
+                trees: 37,
+                lumberyards: 31
+            }
+        );
+        assert_eq!(
+            history.render(10),
+            r".||##.....
+||###.....
+||##......
+|##.....##
+|##.....##
+|##....##|
+||##.####|
+||#####|||
+||||#|||||
+||||||||||"
+        );
+
+        for minute in [0, 1, 10, 100, 1_000, 1_000_000_000] {
+            assert_eq!(
+                history.counts_at(minute).resource_value(),
+                resource_value_after(&scan, minute)
+            );
+            assert_eq!(
+                history.scan_at(minute),
+                lumber_automaton().advance(&Grid::from_rows(&scan), minute)
+            );
+        }
+    }
+
+    #[test]
+    fn history_with_fingerprint_collisions() {
+        let scan = parse_input(TEST_INPUT);
+        let history = LumberHistory::record(&scan);
+        let colliding_history =
+            LumberHistory::record_with(&scan, |scan| ResourceCounts::of(scan).trees as u64 % 2);
+
+        assert_eq!(colliding_history.cycle(), history.cycle());
+
+        for minute in [0, 1, 10, 100, 1_000, 1_000_000_000] {
+            assert_eq!(
+                colliding_history.counts_at(minute),
+                history.counts_at(minute)
+            );
+            assert_eq!(colliding_history.scan_at(minute), history.scan_at(minute));
+        }
+    }
+
+    #[test]
+    fn history_csv() {
+        let history = LumberHistory::record(&parse_input(TEST_INPUT));
+        let csv = history.to_csv();
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some("minute,trees,lumberyards,resource_value,in_cycle")
+        );
+        assert_eq!(lines.next(), Some("0,27,17,459,false"));
+        assert_eq!(
+            csv.lines().count(),
+            1 + history.cycle().start + history.cycle().length
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use crate::image::ppm;
 use aoc_runner_derive::aoc;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -5,9 +6,9 @@ use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
-struct Coordinates {
-    x: i32,
-    y: i32,
+pub struct Coordinates {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl PartialOrd for Coordinates {
@@ -37,144 +38,784 @@ impl From<(i32, i32)> for Coordinates {
     }
 }
 
-fn match_regex(
-    regex: &str,
-    starting_token_index: usize,
-    doors: &mut HashMap<Coordinates, HashSet<Coordinates>>,
-    starting_rooms: &HashSet<Coordinates>,
-) -> (HashSet<Coordinates>, usize) {
-    let mut current_index = starting_token_index;
-    let mut final_rooms: HashSet<Coordinates> = HashSet::new();
-    let mut current_rooms = starting_rooms.clone();
-
-    while current_index < regex.len() {
-        let next_token = regex.chars().nth(current_index).unwrap();
-
-        match next_token {
-            '^' => current_index += 1,
-            'N' | 'S' | 'E' | 'W' => {
-                let mut next_rooms = HashSet::new();
-
-                for room in current_rooms.iter() {
-                    let next_room = match next_token {
-                        'N' => (room.x, room.y - 1).into(),
-                        'S' => (room.x, room.y + 1).into(),
-                        'E' => (room.x + 1, room.y).into(),
-                        'W' => (room.x - 1, room.y).into(),
-                        _ => unreachable!(),
-                    };
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
 
-                    doors.entry(*room).or_default().insert(next_room);
-                    doors.entry(next_room).or_default().insert(*room);
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RouteExpr {
+    Step(Direction),
+    Sequence(Vec<RouteExpr>),
+    Alternation(Vec<RouteExpr>),
+    Empty,
+}
 
-                    next_rooms.insert(next_room);
-                }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SyntaxErrorKind {
+    MissingStart,
+    MissingEnd,
+    UnexpectedCharacter(char),
+    UnclosedParenthesis,
+    UnmatchedParenthesis,
+    TrailingCharacters,
+}
 
-                current_rooms = next_rooms;
-                current_index += 1;
-            }
-            '(' => {
-                let (rooms, index) = match_regex(regex, current_index + 1, doors, &current_rooms);
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxError {
+    pub position: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use SyntaxErrorKind::*;
 
-                current_rooms = rooms;
-                current_index = index;
+        match self.kind {
+            MissingStart => write!(f, "expected '^' at position {}", self.position),
+            MissingEnd => write!(f, "expected '$' at position {}", self.position),
+            UnexpectedCharacter(char) => {
+                write!(f, "unexpected {char:?} at position {}", self.position)
+            }
+            UnclosedParenthesis => {
+                write!(f, "unclosed '(' at position {}", self.position)
+            }
+            UnmatchedParenthesis => {
+                write!(f, "unmatched ')' at position {}", self.position)
             }
-            '|' => {
-                final_rooms.extend(current_rooms.clone());
-                current_rooms = starting_rooms.clone();
-                current_index += 1;
+            TrailingCharacters => {
+                write!(
+                    f,
+                    "unexpected input after '$' at position {}",
+                    self.position
+                )
             }
-            ')' => {
-                final_rooms.extend(current_rooms.clone());
-                current_index += 1;
+        }
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+impl Direction {
+    fn between(from: Coordinates, to: Coordinates) -> Option<Self> {
+        use Direction::*;
+
+        match (to.x - from.x, to.y - from.y) {
+            (0, -1) => Some(North),
+            (0, 1) => Some(South),
+            (1, 0) => Some(East),
+            (-1, 0) => Some(West),
+            _ => None,
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        use Direction::*;
+
+        match self {
+            North => South,
+            South => North,
+            East => West,
+            West => East,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        use Direction::*;
+
+        match self {
+            North => 'N',
+            South => 'S',
+            East => 'E',
+            West => 'W',
+        }
+    }
+}
+
+impl Display for RouteExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use RouteExpr::*;
+
+        match self {
+            Step(direction) => write!(f, "{}", direction.symbol()),
+            Sequence(items) => items.iter().try_for_each(|item| write!(f, "{item}")),
+            Alternation(options) => {
+                write!(f, "(")?;
+
+                for (index, option) in options.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, "|")?;
+                    }
+
+                    write!(f, "{option}")?;
+                }
 
-                return (final_rooms, current_index);
+                write!(f, ")")
             }
-            '$' => {
-                return (final_rooms, current_index);
+            Empty => Ok(()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    regex: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.regex.as_bytes().get(self.position).copied()
+    }
+
+    fn error(&self, kind: SyntaxErrorKind) -> SyntaxError {
+        SyntaxError {
+            position: self.position,
+            kind,
+        }
+    }
+
+    fn parse_route(&mut self) -> Result<RouteExpr, SyntaxError> {
+        use SyntaxErrorKind::*;
+
+        if self.peek() != Some(b'^') {
+            return Err(self.error(MissingStart));
+        }
+
+        self.position += 1;
+
+        let route = self.parse_alternation()?;
+
+        match self.peek() {
+            Some(b'$') => self.position += 1,
+            Some(b')') => return Err(self.error(UnmatchedParenthesis)),
+            _ => return Err(self.error(MissingEnd)),
+        }
+
+        if self.position < self.regex.len() {
+            return Err(self.error(TrailingCharacters));
+        }
+
+        Ok(route)
+    }
+
+    fn parse_alternation(&mut self) -> Result<RouteExpr, SyntaxError> {
+        let mut options = vec![self.parse_sequence()?];
+
+        while self.peek() == Some(b'|') {
+            self.position += 1;
+            options.push(self.parse_sequence()?);
+        }
+
+        if options.len() == 1 {
+            Ok(options.pop().unwrap())
+        } else {
+            Ok(RouteExpr::Alternation(options))
+        }
+    }
+
+    fn parse_sequence(&mut self) -> Result<RouteExpr, SyntaxError> {
+        use Direction::*;
+        use SyntaxErrorKind::*;
+
+        let mut items = Vec::new();
+
+        while let Some(token) = self.peek() {
+            match token {
+                b'N' | b'S' | b'E' | b'W' => {
+                    items.push(RouteExpr::Step(match token {
+                        b'N' => North,
+                        b'S' => South,
+                        b'E' => East,
+                        b'W' => West,
+                        _ => unreachable!(),
+                    }));
+
+                    self.position += 1;
+                }
+                b'(' => {
+                    let opening_position = self.position;
+                    self.position += 1;
+
+                    items.push(self.parse_alternation()?);
+
+                    if self.peek() != Some(b')') {
+                        return Err(SyntaxError {
+                            position: opening_position,
+                            kind: UnclosedParenthesis,
+                        });
+                    }
+
+                    self.position += 1;
+                }
+                b'|' | b')' | b'$' => break,
+                _ => {
+                    let char = self.regex[self.position..].chars().next().unwrap();
+                    return Err(self.error(UnexpectedCharacter(char)));
+                }
             }
-            _ => unreachable!(),
         }
+
+        Ok(if items.is_empty() {
+            RouteExpr::Empty
+        } else {
+            RouteExpr::Sequence(items)
+        })
     }
+}
 
-    unreachable!()
+pub fn parse_regex(regex: &str) -> Result<RouteExpr, SyntaxError> {
+    Parser {
+        regex: regex.trim_end(),
+        position: 0,
+    }
+    .parse_route()
 }
 
-struct State {
-    coordinates: Coordinates,
-    path_len: usize,
+fn walk_route(
+    route: &RouteExpr,
+    doors: &mut HashMap<Coordinates, HashSet<Coordinates>>,
+    starting_rooms: &HashSet<Coordinates>,
+) -> HashSet<Coordinates> {
+    use Direction::*;
+    use RouteExpr::*;
+
+    match route {
+        Step(direction) => {
+            let mut next_rooms = HashSet::new();
+
+            for room in starting_rooms.iter() {
+                let next_room = match direction {
+                    North => (room.x, room.y - 1).into(),
+                    South => (room.x, room.y + 1).into(),
+                    East => (room.x + 1, room.y).into(),
+                    West => (room.x - 1, room.y).into(),
+                };
+
+                doors.entry(*room).or_default().insert(next_room);
+                doors.entry(next_room).or_default().insert(*room);
+
+                next_rooms.insert(next_room);
+            }
+
+            next_rooms
+        }
+        Sequence(items) => items
+            .iter()
+            .fold(starting_rooms.clone(), |current_rooms, item| {
+                walk_route(item, doors, &current_rooms)
+            }),
+        Alternation(options) => options
+            .iter()
+            .flat_map(|option| walk_route(option, doors, starting_rooms))
+            .collect(),
+        Empty => starting_rooms.clone(),
+    }
 }
 
-#[aoc(day20, part1)]
-fn part1(regex: &str) -> usize {
+pub fn match_regex(regex: &str) -> Result<HashMap<Coordinates, HashSet<Coordinates>>, SyntaxError> {
+    let route = parse_regex(regex)?;
     let mut doors = HashMap::new();
 
-    match_regex(regex, 0, &mut doors, &HashSet::from([(0, 0).into()]));
+    walk_route(&route, &mut doors, &HashSet::from([(0, 0).into()]));
 
-    let mut visited: HashSet<Coordinates> = HashSet::from([(0, 0).into()]);
+    Ok(doors)
+}
+
+struct State {
+    coordinates: Coordinates,
+    path_len: usize,
+}
+
+fn room_distances(
+    doors: &HashMap<Coordinates, HashSet<Coordinates>>,
+    starting_room: Coordinates,
+) -> HashMap<Coordinates, usize> {
+    let mut distances = HashMap::from([(starting_room, 0)]);
     let mut queue: VecDeque<State> = VecDeque::from([State {
-        coordinates: (0, 0).into(),
+        coordinates: starting_room,
         path_len: 0,
     }]);
 
-    let mut longest_path = 0;
-
     while let Some(state) = queue.pop_front() {
         for adjacent_room in doors.get(&state.coordinates).unwrap_or(&HashSet::default()) {
-            if !visited.contains(adjacent_room) {
+            if !distances.contains_key(adjacent_room) {
                 let new_path_len = state.path_len + 1;
 
                 queue.push_back(State {
                     coordinates: *adjacent_room,
                     path_len: new_path_len,
                 });
-                visited.insert(*adjacent_room);
+                distances.insert(*adjacent_room, new_path_len);
+            }
+        }
+    }
+
+    distances
+}
+
+// Room (x, y) sits at column 2x + 1 and row 2y + 1 counted from the top-left room.
+fn map_layout(doors: &HashMap<Coordinates, HashSet<Coordinates>>) -> (Coordinates, usize, usize) {
+    let origin: Coordinates = (0, 0).into();
+    let rooms = doors.keys().chain(std::iter::once(&origin));
+
+    let min_x = rooms.clone().map(|room| room.x).min().unwrap();
+    let max_x = rooms.clone().map(|room| room.x).max().unwrap();
+    let min_y = rooms.clone().map(|room| room.y).min().unwrap();
+    let max_y = rooms.map(|room| room.y).max().unwrap();
+
+    (
+        (min_x, min_y).into(),
+        (2 * (max_x - min_x) + 3) as usize,
+        (2 * (max_y - min_y) + 3) as usize,
+    )
+}
+
+fn map_cell_rooms(
+    doors: &HashMap<Coordinates, HashSet<Coordinates>>,
+    top_left: Coordinates,
+    column: usize,
+    row: usize,
+) -> Vec<Coordinates> {
+    let origin: Coordinates = (0, 0).into();
+    let x = top_left.x + (column as i32 - 1).div_euclid(2);
+    let y = top_left.y + (row as i32 - 1).div_euclid(2);
+
+    match (column % 2, row % 2) {
+        (1, 1) => {
+            let room = (x, y).into();
 
-                if new_path_len > longest_path {
-                    longest_path = new_path_len;
+            if doors.contains_key(&room) || room == origin {
+                vec![room]
+            } else {
+                vec![]
+            }
+        }
+        (0, 1) => {
+            let (west, east) = ((x, y).into(), (x + 1, y).into());
+
+            if doors.get(&west).is_some_and(|rooms| rooms.contains(&east)) {
+                vec![west, east]
+            } else {
+                vec![]
+            }
+        }
+        (1, 0) => {
+            let (north, south) = ((x, y).into(), (x, y + 1).into());
+
+            if doors
+                .get(&north)
+                .is_some_and(|rooms| rooms.contains(&south))
+            {
+                vec![north, south]
+            } else {
+                vec![]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+pub fn render_map(doors: &HashMap<Coordinates, HashSet<Coordinates>>) -> String {
+    let (top_left, width, height) = map_layout(doors);
+
+    let mut map = String::with_capacity((width + 1) * height);
+
+    for row in 0..height {
+        if row > 0 {
+            map.push('\n');
+        }
+
+        for column in 0..width {
+            let rooms = map_cell_rooms(doors, top_left, column, row);
+
+            map.push(match (rooms.as_slice(), column % 2) {
+                ([], _) => '#',
+                ([room], _) if *room == (0, 0).into() => 'X',
+                ([_], _) => '.',
+                (_, 0) => '|',
+                (_, _) => '-',
+            });
+        }
+    }
+
+    map
+}
+
+pub fn distance_heatmap(doors: &HashMap<Coordinates, HashSet<Coordinates>>) -> Vec<u8> {
+    let (top_left, width, height) = map_layout(doors);
+    let distances = room_distances(doors, (0, 0).into());
+    let longest_path = distances.values().copied().max().unwrap_or(0).max(1);
+
+    let pixels = (0..height).flat_map(|row| (0..width).map(move |column| (column, row)));
+
+    ppm(
+        width,
+        height,
+        pixels.map(|(column, row)| {
+            let distance = map_cell_rooms(doors, top_left, column, row)
+                .iter()
+                .filter_map(|room| distances.get(room))
+                .min()
+                .copied();
+
+            match distance {
+                None => [0, 0, 0],
+                Some(0) => [255, 255, 255],
+                Some(distance) => {
+                    let heat = (255 * distance / longest_path) as u8;
+
+                    [heat, 0, 255 - heat]
                 }
             }
+        }),
+    )
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MissingOrigin;
+
+impl Display for MissingOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the map has no 'X' room")
+    }
+}
+
+impl std::error::Error for MissingOrigin {}
+
+pub fn parse_map(map: &str) -> Result<HashMap<Coordinates, HashSet<Coordinates>>, MissingOrigin> {
+    let rows: Vec<&[u8]> = map.lines().map(|line| line.as_bytes()).collect();
+
+    let (origin_column, origin_row) = rows
+        .iter()
+        .enumerate()
+        .find_map(|(row, line)| {
+            line.iter()
+                .position(|char| *char == b'X')
+                .map(|column| (column as i32, row as i32))
+        })
+        .ok_or(MissingOrigin)?;
+
+    let room = |column: i32, row: i32| -> Coordinates {
+        ((column - origin_column) / 2, (row - origin_row) / 2).into()
+    };
+
+    let mut doors: HashMap<Coordinates, HashSet<Coordinates>> = HashMap::new();
+
+    for (row, line) in rows.iter().enumerate() {
+        for (column, char) in line.iter().enumerate() {
+            let (column, row) = (column as i32, row as i32);
+
+            let (room_1, room_2) = match char {
+                b'|' => (room(column - 1, row), room(column + 1, row)),
+                b'-' => (room(column, row - 1), room(column, row + 1)),
+                _ => continue,
+            };
+
+            doors.entry(room_1).or_default().insert(room_2);
+            doors.entry(room_2).or_default().insert(room_1);
         }
     }
 
-    longest_path
+    Ok(doors)
 }
 
-const LONG_PATH_LEN: usize = 1_000;
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RegexStyle {
+    Branches,
+    // Side branches become detours like `(NEWS|)` and the route follows the deepest branch.
+    Detours,
+}
 
-#[aoc(day20, part2)]
-fn part2(regex: &str) -> usize {
-    let mut doors = HashMap::new();
+struct SpanningTree {
+    children: HashMap<Coordinates, Vec<(Direction, Coordinates)>>,
+    heights: HashMap<Coordinates, usize>,
+}
 
-    match_regex(regex, 0, &mut doors, &HashSet::from([(0, 0).into()]));
+// Kept on an explicit stack, as recursing down a long corridor would overflow the call stack.
+enum Walk {
+    Route(Coordinates),
+    Detour(Coordinates),
+    Step(Direction),
+    Open,
+    CloseSequence,
+    CloseAlternation,
+    CloseDetour,
+}
 
-    let mut visited: HashSet<Coordinates> = HashSet::from([(0, 0).into()]);
-    let mut queue: VecDeque<State> = VecDeque::from([State {
-        coordinates: (0, 0).into(),
-        path_len: 0,
-    }]);
+impl SpanningTree {
+    fn new(doors: &HashMap<Coordinates, HashSet<Coordinates>>) -> Self {
+        use Direction::*;
 
-    let mut far_rooms_count = 0;
+        let origin: Coordinates = (0, 0).into();
 
-    while let Some(state) = queue.pop_front() {
-        for adjacent_room in doors.get(&state.coordinates).unwrap_or(&HashSet::default()) {
-            if !visited.contains(adjacent_room) {
-                let new_path_len = state.path_len + 1;
+        let mut children: HashMap<Coordinates, Vec<(Direction, Coordinates)>> = HashMap::new();
+        let mut visited = HashSet::from([origin]);
+        let mut queue = VecDeque::from([origin]);
+        let mut order = Vec::new();
 
-                queue.push_back(State {
-                    coordinates: *adjacent_room,
-                    path_len: new_path_len,
-                });
-                visited.insert(*adjacent_room);
+        while let Some(room) = queue.pop_front() {
+            order.push(room);
+
+            for direction in [North, East, South, West] {
+                let adjacent_room = match direction {
+                    North => (room.x, room.y - 1).into(),
+                    South => (room.x, room.y + 1).into(),
+                    East => (room.x + 1, room.y).into(),
+                    West => (room.x - 1, room.y).into(),
+                };
+
+                if doors
+                    .get(&room)
+                    .is_some_and(|rooms| rooms.contains(&adjacent_room))
+                    && visited.insert(adjacent_room)
+                {
+                    children
+                        .entry(room)
+                        .or_default()
+                        .push((direction, adjacent_room));
+                    queue.push_back(adjacent_room);
+                }
+            }
+        }
+
+        // Reversed breadth-first order visits children before their parents.
+        let mut heights: HashMap<Coordinates, usize> = HashMap::new();
+
+        for room in order.into_iter().rev() {
+            let height = children.get(&room).map_or(0, |children| {
+                children
+                    .iter()
+                    .map(|(_, child)| heights[child] + 1)
+                    .max()
+                    .unwrap()
+            });
+
+            heights.insert(room, height);
+        }
+
+        SpanningTree { children, heights }
+    }
+
+    fn children(&self, room: Coordinates) -> &[(Direction, Coordinates)] {
+        self.children.get(&room).map_or(&[], |children| children)
+    }
+
+    fn route(&self, style: RegexStyle) -> RouteExpr {
+        use Walk::*;
+
+        let mut sequences = vec![Vec::new()];
+        let mut walk = vec![Route((0, 0).into())];
+
+        while let Some(piece) = walk.pop() {
+            match piece {
+                Route(room) => {
+                    let children = self.children(room);
+
+                    match (children, style) {
+                        ([], _) => {}
+                        ([(direction, child)], _) => {
+                            walk.extend([Route(*child), Step(*direction)]);
+                        }
+                        (_, RegexStyle::Branches) => {
+                            walk.push(CloseAlternation);
+
+                            for (direction, child) in children.iter().rev() {
+                                walk.extend([CloseSequence, Route(*child), Step(*direction), Open]);
+                            }
+
+                            walk.push(Open);
+                        }
+                        (_, RegexStyle::Detours) => {
+                            let (main_index, _) = children
+                                .iter()
+                                .enumerate()
+                                .rev()
+                                .max_by_key(|(_, (_, child))| self.heights[child])
+                                .unwrap();
+                            let (direction, child) = children[main_index];
 
-                if new_path_len >= LONG_PATH_LEN {
-                    far_rooms_count += 1;
+                            walk.extend([Route(child), Step(direction)]);
+
+                            for (index, (direction, child)) in children.iter().enumerate().rev() {
+                                if index != main_index {
+                                    walk.extend([
+                                        CloseDetour,
+                                        Step(direction.opposite()),
+                                        Detour(*child),
+                                        Step(*direction),
+                                        Open,
+                                    ]);
+                                }
+                            }
+                        }
+                    }
+                }
+                Detour(room) => {
+                    for (direction, child) in self.children(room).iter().rev() {
+                        walk.extend([Step(direction.opposite()), Detour(*child), Step(*direction)]);
+                    }
+                }
+                Step(direction) => sequences
+                    .last_mut()
+                    .unwrap()
+                    .push(RouteExpr::Step(direction)),
+                Open => sequences.push(Vec::new()),
+                CloseSequence | CloseAlternation | CloseDetour => {
+                    let items = sequences.pop().unwrap();
+                    let expr = match piece {
+                        CloseSequence => RouteExpr::Sequence(items),
+                        CloseAlternation => RouteExpr::Alternation(items),
+                        _ => RouteExpr::Alternation(vec![
+                            RouteExpr::Sequence(items),
+                            RouteExpr::Empty,
+                        ]),
+                    };
+
+                    sequences.last_mut().unwrap().push(expr);
                 }
             }
         }
+
+        RouteExpr::Sequence(sequences.pop().unwrap())
+    }
+}
+
+pub fn emit_regex(doors: &HashMap<Coordinates, HashSet<Coordinates>>, style: RegexStyle) -> String {
+    format!("^{}$", SpanningTree::new(doors).route(style))
+}
+
+pub struct Facility {
+    doors: HashMap<Coordinates, HashSet<Coordinates>>,
+    distances: HashMap<Coordinates, usize>,
+}
+
+impl Facility {
+    pub fn from_regex(regex: &str) -> Result<Self, SyntaxError> {
+        let doors = match_regex(regex)?;
+        let distances = room_distances(&doors, (0, 0).into());
+
+        Ok(Facility { doors, distances })
+    }
+
+    pub fn doors(&self) -> &HashMap<Coordinates, HashSet<Coordinates>> {
+        &self.doors
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn distance(&self, room: Coordinates) -> Option<usize> {
+        self.distances.get(&room).copied()
+    }
+
+    pub fn shortest_path(&self, from: Coordinates, to: Coordinates) -> Option<String> {
+        if !self.distances.contains_key(&from) || !self.distances.contains_key(&to) {
+            return None;
+        }
+
+        let distances_to_target = room_distances(&self.doors, to);
+        let mut remaining = *distances_to_target.get(&from)?;
+
+        let mut path = String::with_capacity(remaining);
+        let mut room = from;
+
+        while remaining > 0 {
+            let next_room = self.doors[&room]
+                .iter()
+                .filter(|adjacent_room| {
+                    distances_to_target.get(adjacent_room) == Some(&(remaining - 1))
+                })
+                .min()
+                .copied()
+                .unwrap();
+
+            path.push(Direction::between(room, next_room).unwrap().symbol());
+
+            room = next_room;
+            remaining -= 1;
+        }
+
+        Some(path)
+    }
+
+    pub fn rooms_within(&self, from: Coordinates, max_doors: usize) -> Vec<Coordinates> {
+        if !self.distances.contains_key(&from) {
+            return Vec::new();
+        }
+
+        let distances_from_room;
+        let distances = if from == (0, 0).into() {
+            &self.distances
+        } else {
+            distances_from_room = room_distances(&self.doors, from);
+            &distances_from_room
+        };
+
+        let mut rooms: Vec<Coordinates> = distances
+            .iter()
+            .filter_map(|(room, distance)| (*distance <= max_doors).then_some(*room))
+            .collect();
+
+        rooms.sort_unstable();
+        rooms
+    }
+
+    pub fn farthest_room(&self) -> (Coordinates, usize) {
+        self.distances
+            .iter()
+            .map(|(room, distance)| (*room, *distance))
+            .max_by(|(room_1, distance_1), (room_2, distance_2)| {
+                distance_1.cmp(distance_2).then(room_2.cmp(room_1))
+            })
+            .unwrap()
+    }
+
+    pub fn eccentricity(&self, room: Coordinates) -> Option<usize> {
+        if !self.distances.contains_key(&room) {
+            return None;
+        }
+
+        room_distances(&self.doors, room).into_values().max()
     }
 
-    far_rooms_count
+    // Two searches suffice on a tree of doors; otherwise every room is searched from.
+    pub fn diameter(&self) -> usize {
+        let door_count = self.doors.values().map(HashSet::len).sum::<usize>() / 2;
+
+        if door_count + 1 == self.room_count() {
+            let (end, _) = self.farthest_room();
+
+            return self.eccentricity(end).unwrap();
+        }
+
+        self.distances
+            .keys()
+            .filter_map(|room| self.eccentricity(*room))
+            .max()
+            .unwrap()
+    }
+}
+
+#[aoc(day20, part1)]
+fn part1(regex: &str) -> Result<usize, SyntaxError> {
+    Ok(Facility::from_regex(regex)?.farthest_room().1)
+}
+
+const LONG_PATH_LEN: usize = 1_000;
+
+#[aoc(day20, part2)]
+fn part2(regex: &str) -> Result<usize, SyntaxError> {
+    let facility = Facility::from_regex(regex)?;
+
+    Ok(facility
+        .distances
+        .values()
+        .filter(|distance| **distance >= LONG_PATH_LEN)
+        .count())
 }
 
 #[cfg(test)]
@@ -189,26 +830,229 @@ mod tests {
 
     #[test]
     fn part1_example1() {
-        assert_eq!(part1(TEST_INPUT_1), 3);
+        assert_eq!(part1(TEST_INPUT_1).unwrap(), 3);
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(part1(TEST_INPUT_2), 10);
+        assert_eq!(part1(TEST_INPUT_2).unwrap(), 10);
     }
 
     #[test]
     fn part1_example3() {
-        assert_eq!(part1(TEST_INPUT_3), 18);
+        assert_eq!(part1(TEST_INPUT_3).unwrap(), 18);
     }
 
     #[test]
     fn part1_example4() {
-        assert_eq!(part1(TEST_INPUT_4), 23);
+        assert_eq!(part1(TEST_INPUT_4).unwrap(), 23);
     }
 
     #[test]
     fn part1_example5() {
-        assert_eq!(part1(TEST_INPUT_5), 31);
+        assert_eq!(part1(TEST_INPUT_5).unwrap(), 31);
+    }
+
+    #[test]
+    fn parse_example() {
+        use Direction::*;
+        use RouteExpr::*;
+
+        assert_eq!(
+            parse_regex("^EN(W|S|)$"),
+            Ok(Sequence(vec![
+                Step(East),
+                Step(North),
+                Alternation(vec![
+                    Sequence(vec![Step(West)]),
+                    Sequence(vec![Step(South)]),
+                    Empty
+                ])
+            ]))
+        );
+    }
+
+    #[test]
+    fn syntax_errors() {
+        use SyntaxErrorKind::*;
+
+        let error = |position, kind| Err(SyntaxError { position, kind });
+
+        assert_eq!(parse_regex("ENWS$"), error(0, MissingStart));
+        assert_eq!(parse_regex("^ENWS"), error(5, MissingEnd));
+        assert_eq!(parse_regex("^EN(W|S$"), error(3, UnclosedParenthesis));
+        assert_eq!(parse_regex("^EN)WS$"), error(3, UnmatchedParenthesis));
+        assert_eq!(parse_regex("^ENxWS$"), error(3, UnexpectedCharacter('x')));
+        assert_eq!(parse_regex("^ENWS$N"), error(6, TrailingCharacters));
+    }
+
+    #[test]
+    fn render_map_examples() {
+        assert_eq!(
+            render_map(&match_regex(TEST_INPUT_1).unwrap()),
+            r"#####
+#.|.#
+#-###
+#.|X#
+#####"
+        );
+        assert_eq!(
+            render_map(&match_regex(TEST_INPUT_2).unwrap()),
+            r"#########
+#.|.|.|.#
+#-#######
+#.|.|.|.#
+#-#####-#
+#.#.#X|.#
+#-#-#####
+#.|.|.|.#
+#########"
+        );
+        assert_eq!(
+            render_map(&match_regex(TEST_INPUT_3).unwrap()),
+            r"###########
+#.|.#.|.#.#
+#-###-#-#-#
+#.|.|.#.#.#
+#-#####-#-#
+#.#.#X|.#.#
+#-#-#####-#
+#.#.|.|.|.#
+#-###-###-#
+#.|.|.#.|.#
+###########"
+        );
+    }
+
+    #[test]
+    fn distance_heatmap_example() {
+        let image = distance_heatmap(&match_regex(TEST_INPUT_1).unwrap());
+        let header = b"P6\n5 5\n255\n";
+
+        assert!(image.starts_with(header));
+        assert_eq!(image.len(), header.len() + 5 * 5 * 3);
+
+        let pixel = |column: usize, row: usize| {
+            let offset = header.len() + (row * 5 + column) * 3;
+            image[offset..offset + 3].to_vec()
+        };
+
+        assert_eq!(pixel(0, 0), [0, 0, 0]);
+        assert_eq!(pixel(3, 3), [255, 255, 255]);
+        assert_eq!(pixel(1, 1), [170, 0, 85]);
+        assert_eq!(pixel(3, 1), [255, 0, 0]);
+    }
+
+    #[test]
+    fn facility_queries() {
+        let facility = Facility::from_regex(TEST_INPUT_2).unwrap();
+
+        assert_eq!(facility.farthest_room(), ((1, 1).into(), 10));
+        assert_eq!(
+            facility.shortest_path((0, 0).into(), (1, 1).into()),
+            Some("ENWWWSSEEE".to_string())
+        );
+        assert_eq!(
+            facility.shortest_path((1, -2).into(), (-1, 0).into()),
+            Some("WWWSSSEN".to_string())
+        );
+        assert_eq!(facility.shortest_path((0, 0).into(), (5, 5).into()), None);
+        assert_eq!(
+            facility.rooms_within((0, 0).into(), 1),
+            vec![(0, 0).into(), (1, 0).into()]
+        );
+        assert_eq!(facility.eccentricity((0, 0).into()), Some(10));
+        assert_eq!(facility.diameter(), 10);
+    }
+
+    #[test]
+    fn facility_unknown_rooms() {
+        let facility = Facility::from_regex(TEST_INPUT_2).unwrap();
+
+        assert_eq!(facility.rooms_within((5, 5).into(), 3), vec![]);
+        assert_eq!(facility.shortest_path((5, 5).into(), (5, 5).into()), None);
+        assert_eq!(facility.shortest_path((5, 5).into(), (0, 0).into()), None);
+    }
+
+    #[test]
+    fn diameter_with_loops() {
+        assert_eq!(Facility::from_regex("^ENWS$").unwrap().diameter(), 2);
+        assert_eq!(Facility::from_regex("^EEENWWWS$").unwrap().diameter(), 4);
+    }
+
+    #[test]
+    fn emit_regex_examples() {
+        assert_eq!(
+            emit_regex(&match_regex(TEST_INPUT_2).unwrap(), RegexStyle::Branches),
+            "^ENWWW(NEEE|SSE(N|EE))$"
+        );
+        assert_eq!(
+            emit_regex(&match_regex(TEST_INPUT_3).unwrap(), RegexStyle::Detours),
+            "^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$"
+        );
+    }
+
+    #[test]
+    fn emit_regex_round_trip() {
+        for regex in [
+            TEST_INPUT_1,
+            TEST_INPUT_2,
+            TEST_INPUT_3,
+            TEST_INPUT_4,
+            TEST_INPUT_5,
+        ] {
+            let map = render_map(&match_regex(regex).unwrap());
+            let doors = parse_map(&map).unwrap();
+
+            assert_eq!(doors, match_regex(regex).unwrap());
+
+            for style in [RegexStyle::Branches, RegexStyle::Detours] {
+                assert_eq!(
+                    render_map(&match_regex(&emit_regex(&doors, style)).unwrap()),
+                    map
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn emit_regex_spanning_tree() {
+        let doors = parse_map(
+            r"#####
+#.|.#
+#-#-#
+#X|.#
+#####",
+        )
+        .unwrap();
+        let regex = emit_regex(&doors, RegexStyle::Branches);
+
+        assert_eq!(regex, "^(NE|E)$");
+
+        // The spanning tree keeps every room but drops one of the four doors of the loop.
+        let tree = match_regex(&regex).unwrap();
+
+        assert_eq!(tree.len(), doors.len());
+        assert_eq!(tree.values().map(|rooms| rooms.len()).sum::<usize>(), 2 * 3);
+    }
+
+    #[test]
+    fn emit_regex_long_corridor() {
+        let regex = format!("^{}$", "E".repeat(4000));
+        let doors = match_regex(&regex).unwrap();
+
+        for style in [RegexStyle::Branches, RegexStyle::Detours] {
+            assert_eq!(emit_regex(&doors, style), regex);
+        }
+
+        let doors = match_regex(&format!("^(N|S{})$", "E".repeat(4000))).unwrap();
+        let detours = format!("^(NS|)S{}$", "E".repeat(4000));
+
+        assert_eq!(emit_regex(&doors, RegexStyle::Detours), detours);
+    }
+
+    #[test]
+    fn parse_map_without_origin() {
+        assert_eq!(parse_map("###\n#.#\n###"), Err(MissingOrigin));
     }
 }
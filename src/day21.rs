@@ -1,3 +1,4 @@
+use crate::cycle::find_cycle;
 use aoc_runner_derive::{aoc, aoc_generator};
 use Opcode::*;
 
@@ -61,8 +62,76 @@ fn parse_input(input: &str) -> Program {
     parser.parse(input).unwrap()
 }
 
-fn execute_program(program: &Program, memory: &mut MemoryState, halt_at: Option<usize>) {
-    let mut ip_value = 0;
+fn execute_instruction(program: &Program, memory: &mut MemoryState, ip_value: usize) -> usize {
+    let instruction = &program.instructions[ip_value];
+
+    memory[program.ip_bound_to] = ip_value;
+    memory[instruction.3] = match instruction.0 {
+        Addr => memory[instruction.1] + memory[instruction.2],
+        Addi => memory[instruction.1] + instruction.2,
+        Mulr => memory[instruction.1] * memory[instruction.2],
+        Muli => memory[instruction.1] * instruction.2,
+        Banr => memory[instruction.1] & memory[instruction.2],
+        Bani => memory[instruction.1] & instruction.2,
+        Borr => memory[instruction.1] | memory[instruction.2],
+        Bori => memory[instruction.1] | instruction.2,
+        Setr => memory[instruction.1],
+        Seti => instruction.1,
+        Gtir => {
+            if instruction.1 > memory[instruction.2] {
+                1
+            } else {
+                0
+            }
+        }
+        Gtri => {
+            if memory[instruction.1] > instruction.2 {
+                1
+            } else {
+                0
+            }
+        }
+        Gtrr => {
+            if memory[instruction.1] > memory[instruction.2] {
+                1
+            } else {
+                0
+            }
+        }
+        Eqir => {
+            if instruction.1 == memory[instruction.2] {
+                1
+            } else {
+                0
+            }
+        }
+        Eqri => {
+            if memory[instruction.1] == instruction.2 {
+                1
+            } else {
+                0
+            }
+        }
+        Eqrr => {
+            if memory[instruction.1] == memory[instruction.2] {
+                1
+            } else {
+                0
+            }
+        }
+    };
+
+    memory[program.ip_bound_to] + 1
+}
+
+// Stops before executing the instruction at `halt_at`.
+fn execute_program(
+    program: &Program,
+    memory: &mut MemoryState,
+    start_at: usize,
+    halt_at: Option<usize>,
+) {
+    let mut ip_value = start_at;
 
     while ip_value < program.instructions.len() {
         if let Some(value) = halt_at
@@ -71,74 +140,12 @@ fn execute_program(program: &Program, memory: &mut MemoryState, halt_at: Option<
             break;
         }
 
-        let instruction = &program.instructions[ip_value];
-
-        memory[program.ip_bound_to] = ip_value;
-        memory[instruction.3] = match instruction.0 {
-            Addr => memory[instruction.1] + memory[instruction.2],
-            Addi => memory[instruction.1] + instruction.2,
-            Mulr => memory[instruction.1] * memory[instruction.2],
-            Muli => memory[instruction.1] * instruction.2,
-            Banr => memory[instruction.1] & memory[instruction.2],
-            Bani => memory[instruction.1] & instruction.2,
-            Borr => memory[instruction.1] | memory[instruction.2],
-            Bori => memory[instruction.1] | instruction.2,
-            Setr => memory[instruction.1],
-            Seti => instruction.1,
-            Gtir => {
-                if instruction.1 > memory[instruction.2] {
-                    1
-                } else {
-                    0
-                }
-            }
-            Gtri => {
-                if memory[instruction.1] > instruction.2 {
-                    1
-                } else {
-                    0
-                }
-            }
-            Gtrr => {
-                if memory[instruction.1] > memory[instruction.2] {
-                    1
-                } else {
-                    0
-                }
-            }
-            Eqir => {
-                if instruction.1 == memory[instruction.2] {
-                    1
-                } else {
-                    0
-                }
-            }
-            Eqri => {
-                if memory[instruction.1] == instruction.2 {
-                    1
-                } else {
-                    0
-                }
-            }
-            Eqrr => {
-                if memory[instruction.1] == memory[instruction.2] {
-                    1
-                } else {
-                    0
-                }
-            }
-        };
-
-        ip_value = memory[program.ip_bound_to] + 1;
+        ip_value = execute_instruction(program, memory, ip_value);
     }
 }
 
-//This relies heavily on the structure of the specific input. I am unsure if it would work for all possible inputs.
-#[aoc(day21, part1)]
-fn part1(program: &Program) -> usize {
-    let mut memory: MemoryState = [0; 6];
-
-    let halt_at = program
+fn halting_check(program: &Program) -> Option<usize> {
+    program
         .instructions
         .iter()
         .enumerate()
@@ -148,9 +155,17 @@ fn part1(program: &Program) -> usize {
             } else {
                 None
             }
-        });
+        })
+}
 
-    execute_program(program, &mut memory, halt_at);
+//This relies heavily on the structure of the specific input. I am unsure if it would work for all possible inputs.
+#[aoc(day21, part1)]
+fn part1(program: &Program) -> usize {
+    let mut memory: MemoryState = [0; 6];
+
+    let halt_at = halting_check(program);
+
+    execute_program(program, &mut memory, 0, halt_at);
 
     if let Some(ip_value) = halt_at {
         match program.instructions[ip_value] {
@@ -161,3 +176,70 @@ fn part1(program: &Program) -> usize {
 
     memory[0]
 }
+
+#[aoc(day21, part2)]
+fn part2(program: &Program) -> usize {
+    let halt_at = halting_check(program).unwrap();
+
+    let compared_register = match program.instructions[halt_at] {
+        (Eqrr, 0, x, _) | (Eqrr, x, 0, _) => x,
+        _ => unreachable!(),
+    };
+
+    let mut memory: MemoryState = [0; 6];
+    execute_program(program, &mut memory, 0, Some(halt_at));
+
+    let mut compared_values = vec![memory[compared_register]];
+
+    let (first_repeat, _) = find_cycle(
+        &memory,
+        |memory| {
+            let ip_value = execute_instruction(program, memory, halt_at);
+            execute_program(program, memory, ip_value, Some(halt_at));
+            compared_values.push(memory[compared_register]);
+        },
+        |memory| memory[compared_register],
+    );
+
+    compared_values[first_repeat.start + first_repeat.length - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Compares register 0 against 5, 10, 5, ... from r1 = (r1 * r1 + 1) & 15, starting at 2.
+    static TEST_INPUT: &str = r"#ip 5
+seti 2 0 1
+mulr 1 1 1
+addi 1 1 1
+bani 1 15 1
+eqrr 1 0 2
+addr 2 5 5
+seti 0 0 5";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(&parse_input(TEST_INPUT)), 5);
+    }
+
+    #[test]
+    fn part2_example() {
+        assert_eq!(part2(&parse_input(TEST_INPUT)), 10);
+    }
+
+    #[test]
+    fn halts_before_the_instruction() {
+        let program = parse_input(TEST_INPUT);
+
+        let mut memory: MemoryState = [0; 6];
+        execute_program(&program, &mut memory, 0, Some(4));
+
+        assert_eq!(memory, [0, 5, 0, 0, 0, 3]);
+
+        let mut memory: MemoryState = [0; 6];
+        execute_program(&program, &mut memory, 0, Some(0));
+
+        assert_eq!(memory, [0; 6]);
+    }
+}
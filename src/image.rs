@@ -0,0 +1,32 @@
+// Binary PPM, which any image viewer can open and which needs no image crate to write.
+pub fn ppm(width: usize, height: usize, pixels: impl IntoIterator<Item = [u8; 3]>) -> Vec<u8> {
+    let mut image = format!("P6\n{width} {height}\n255\n").into_bytes();
+    let header_len = image.len();
+
+    image.reserve(width * height * 3);
+
+    for pixel in pixels {
+        image.extend_from_slice(&pixel);
+    }
+
+    assert_eq!(
+        image.len() - header_len,
+        width * height * 3,
+        "expected one pixel per cell"
+    );
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ppm_layout() {
+        assert_eq!(
+            ppm(2, 1, [[1, 2, 3], [4, 5, 6]]),
+            b"P6\n2 1\n255\n\x01\x02\x03\x04\x05\x06"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::fmt::{Display, Formatter};
+
+// The 6x10 font of the Day 10 sky messages.
+pub const GLYPHS: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..
+.#..#.
+#....#
+#....#
+#....#
+######
+#....#
+#....#
+#....#
+#....#",
+    ),
+    (
+        'B',
+        "#####.
+#....#
+#....#
+#....#
+#####.
+#....#
+#....#
+#....#
+#....#
+#####.",
+    ),
+    (
+        'C',
+        ".####.
+#....#
+#.....
+#.....
+#.....
+#.....
+#.....
+#.....
+#....#
+.####.",
+    ),
+    (
+        'E',
+        "######
+#.....
+#.....
+#.....
+#####.
+#.....
+#.....
+#.....
+#.....
+######",
+    ),
+    (
+        'F',
+        "######
+#.....
+#.....
+#.....
+#####.
+#.....
+#.....
+#.....
+#.....
+#.....",
+    ),
+    (
+        'G',
+        ".####.
+#....#
+#.....
+#.....
+#.....
+#..###
+#....#
+#....#
+#...##
+.###.#",
+    ),
+    (
+        'H',
+        "#....#
+#....#
+#....#
+#....#
+######
+#....#
+#....#
+#....#
+#....#
+#....#",
+    ),
+    (
+        'J',
+        "...###
+....#.
+....#.
+....#.
+....#.
+....#.
+....#.
+#...#.
+#...#.
+.###..",
+    ),
+    (
+        'K',
+        "#....#
+#...#.
+#..#..
+#.#...
+##....
+##....
+#.#...
+#..#..
+#...#.
+#....#",
+    ),
+    (
+        'L',
+        "#.....
+#.....
+#.....
+#.....
+#.....
+#.....
+#.....
+#.....
+#.....
+######",
+    ),
+    (
+        'N',
+        "#....#
+##...#
+##...#
+#.#..#
+#.#..#
+#..#.#
+#..#.#
+#...##
+#...##
+#....#",
+    ),
+    (
+        'P',
+        "#####.
+#....#
+#....#
+#....#
+#####.
+#.....
+#.....
+#.....
+#.....
+#.....",
+    ),
+    (
+        'R',
+        "#####.
+#....#
+#....#
+#....#
+#####.
+#..#..
+#...#.
+#...#.
+#....#
+#....#",
+    ),
+    (
+        'X',
+        "#....#
+#....#
+.#..#.
+.#..#.
+..##..
+..##..
+.#..#.
+.#..#.
+#....#
+#....#",
+    ),
+    (
+        'Z',
+        "######
+.....#
+.....#
+....#.
+...#..
+..#...
+.#....
+#.....
+#.....
+######",
+    ),
+];
+
+// The smaller letters of the puzzle's example.
+#[cfg(test)]
+pub const EXAMPLE_GLYPHS: &[(char, &str)] = &[
+    (
+        'H',
+        "#...#
+#...#
+#...#
+#####
+#...#
+#...#
+#...#
+#...#",
+    ),
+    (
+        'I',
+        "###
+.#.
+.#.
+.#.
+.#.
+.#.
+.#.
+###",
+    ),
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnreadableGlyph {
+    pub index: usize,
+    pub picture: String,
+}
+
+// `partial` is the message with a '?' in place of every glyph that could not be read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OcrError {
+    pub partial: String,
+    pub glyphs: Vec<UnreadableGlyph>,
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not read all of {:?}", self.partial)?;
+
+        for glyph in self.glyphs.iter() {
+            write!(f, "\n\nglyph {}:\n{}", glyph.index, glyph.picture)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+pub fn recognize(picture: &str) -> Result<String, OcrError> {
+    recognize_with(picture, GLYPHS)
+}
+
+// Glyphs may have any width and are separated by columns with no '#' in them.
+pub fn recognize_with(picture: &str, font: &[(char, &str)]) -> Result<String, OcrError> {
+    let rows: Vec<&[u8]> = picture
+        .lines()
+        .map(str::as_bytes)
+        .filter(|row| !row.is_empty())
+        .collect();
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |x: usize, y: usize| rows[y].get(x) == Some(&b'#');
+    let blank_column = |x: usize| (0..rows.len()).all(|y| !lit(x, y));
+
+    let mut partial = String::new();
+    let mut glyphs = Vec::new();
+    let mut x = 0;
+
+    while x < width {
+        if blank_column(x) {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+
+        while x < width && !blank_column(x) {
+            x += 1;
+        }
+
+        let glyph_picture = (0..rows.len())
+            .map(|y| {
+                (start..x)
+                    .map(|x| if lit(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        match font.iter().find(|(_, picture)| *picture == glyph_picture) {
+            Some((letter, _)) => partial.push(*letter),
+            None => {
+                glyphs.push(UnreadableGlyph {
+                    index: partial.chars().count(),
+                    picture: glyph_picture,
+                });
+                partial.push('?');
+            }
+        }
+    }
+
+    if glyphs.is_empty() {
+        Ok(partial)
+    } else {
+        Err(OcrError { partial, glyphs })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ten_row_font() {
+        let picture = r"#....#..#####...######
+#....#..#....#..#.....
+.#..#...#....#..#.....
+.#..#...#....#..#.....
+..##....#####...#####.
+..##....#..#....#.....
+.#..#...#...#...#.....
+.#..#...#...#...#.....
+#....#..#....#..#.....
+#....#..#....#..######";
+
+        assert_eq!(recognize(picture), Ok(String::from("XRE")));
+    }
+
+    #[test]
+    fn every_glyph() {
+        let rows: Vec<String> = (0..10)
+            .map(|row| {
+                GLYPHS
+                    .iter()
+                    .map(|(_, picture)| picture.lines().nth(row).unwrap())
+                    .collect::<Vec<_>>()
+                    .join("..")
+            })
+            .collect();
+
+        assert!(GLYPHS.iter().all(|(_, picture)| {
+            picture.lines().count() == 10 && picture.lines().all(|row| row.len() == 6)
+        }));
+        assert_eq!(
+            recognize(&rows.join("\n")),
+            Ok(GLYPHS.iter().map(|(letter, _)| *letter).collect())
+        );
+    }
+
+    #[test]
+    fn unreadable_glyph() {
+        let picture = "#...#..#.#\n#...#..###\n#...#..#.#\n#####....\n#...#....\n#...#....\n#...#....\n#...#....";
+
+        assert_eq!(
+            recognize_with(picture, EXAMPLE_GLYPHS),
+            Err(OcrError {
+                partial: String::from("H?"),
+                glyphs: vec![UnreadableGlyph {
+                    index: 1,
+                    picture: String::from("#.#\n###\n#.#\n...\n...\n...\n...\n..."),
+                }],
+            })
+        );
+    }
+}